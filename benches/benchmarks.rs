@@ -0,0 +1,78 @@
+//! Times the programs in `benchmark/` with an optimized build of the interpreter.
+//!
+//! ```text
+//! cargo bench                         # every program
+//! cargo bench -- fib method_call      # only programs whose names contain one of these
+//! TREELOX_BENCH_RUNS=3 cargo bench    # more runs of each, to steady the numbers
+//! ```
+//!
+//! Each program runs in its own process, since they print their results. With more than one run,
+//! the fastest is reported as the one least disturbed by anything else on the machine.
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{exit, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+/// The programs take seconds to minutes each in a tree-walking interpreter, so one run is the
+/// default.
+const DEFAULT_RUNS: usize = 1;
+
+fn main() {
+    // Cargo passes `--bench` along with any filters.
+    let filters: Vec<String> = env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+    let runs = env::var("TREELOX_BENCH_RUNS")
+        .ok()
+        .and_then(|runs| runs.parse().ok())
+        .unwrap_or(DEFAULT_RUNS);
+
+    let mut programs: Vec<PathBuf> = fs::read_dir("benchmark")
+        .expect("benchmarks run from the crate root")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "lox"))
+        .filter(|path| {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            filters.is_empty() || filters.iter().any(|filter| name.contains(filter.as_str()))
+        })
+        .collect();
+    programs.sort();
+
+    let mut failed = false;
+    for program in &programs {
+        let name = program.file_stem().unwrap_or_default().to_string_lossy();
+        match time(program, runs) {
+            Ok(fastest) => println!("{:<20} {:>10.3}s", name, fastest.as_secs_f64()),
+            Err(error) => {
+                println!("{:<20} failed: {}", name, error);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        exit(1)
+    }
+}
+
+/// The fastest of `runs` runs of `program`.
+fn time(program: &PathBuf, runs: usize) -> Result<Duration, String> {
+    let mut fastest = Duration::MAX;
+    for _ in 0..runs.max(1) {
+        let start = Instant::now();
+        let status = Command::new(env!("CARGO_BIN_EXE_treelox"))
+            .arg(program)
+            .stdout(Stdio::null())
+            .status()
+            .map_err(|error| error.to_string())?;
+        let elapsed = start.elapsed();
+        if !status.success() {
+            return Err(format!("exited with {}", status));
+        }
+        fastest = fastest.min(elapsed);
+    }
+    Ok(fastest)
+}
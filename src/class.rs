@@ -1,8 +1,15 @@
-use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    rc::Rc,
+};
 
 use crate::{
     error::Error,
     function::Function,
+    suggest::did_you_mean,
+    symbol::Symbol,
     token::{Object, Token},
 };
 
@@ -23,38 +30,86 @@ impl Class {
             None
         }
     }
+
+    /// Every method an instance of this class can call, including inherited ones.
+    pub fn all_method_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.methods.keys().cloned().collect();
+        if let Some(ref superclass) = self.superclass {
+            names.extend(superclass.borrow().all_method_names());
+        }
+        names
+    }
+
+    pub fn method_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.methods.keys().cloned().collect();
+        names.sort();
+        names
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Instance {
     pub class: Rc<RefCell<Class>>,
-    fields: HashMap<Token, Object>,
+    /// Keyed by name alone, so a field set on one line is found on any other, and kept in name
+    /// order so listing them is deterministic.
+    fields: BTreeMap<Symbol, Object>,
 }
 
 impl Instance {
     pub fn new_object(class: &Rc<RefCell<Class>>) -> Object {
         let instance = Instance {
             class: Rc::clone(class),
-            fields: HashMap::new(),
+            fields: BTreeMap::new(),
         };
         Object::Instance(Rc::new(RefCell::new(instance)))
     }
 
     pub fn get(&self, name: &Token, instance: &Object) -> Result<Object, Error> {
-        if let Some(field) = self.fields.get(name) {
-            Ok(field.clone())
-        } else if let Some(method) = self.class.borrow().find_method(&name.lexeme) {
-            Ok(Object::Callable(method.bind(instance.clone())))
+        if let Some(field) = self.get_field(&name.lexeme, instance) {
+            Ok(field)
         } else {
-            Err(Error::Runtime {
-                token: name.clone(),
-                message: format!("Undefined property '{}'.", name.lexeme),
-            })
+            let mut candidates = self.field_names();
+            candidates.extend(self.class.borrow().all_method_names());
+            Err(Error::runtime(
+                name,
+                "E0302",
+                format!("Undefined property '{}'.", name.lexeme),
+            )
+            .with_hint(did_you_mean(
+                &name.lexeme,
+                candidates.iter().map(String::as_str),
+            )))
         }
     }
 
     pub fn set(&mut self, name: &Token, value: Object) {
-        self.fields.insert(name.clone(), value);
+        self.set_field(&name.lexeme, value);
+    }
+
+    pub fn field_names(&self) -> Vec<String> {
+        self.fields.keys().map(Symbol::to_string).collect()
+    }
+
+    pub fn has_field(&self, name: &str) -> bool {
+        self.fields.contains_key(name)
+    }
+
+    /// Looks up a property by name, binding methods to `instance` like [`Instance::get`].
+    pub fn get_field(&self, name: &str, instance: &Object) -> Option<Object> {
+        self.fields.get(name).cloned().or_else(|| {
+            self.class
+                .borrow()
+                .find_method(name)
+                .map(|method| Object::Callable(method.bind(instance.clone())))
+        })
+    }
+
+    pub fn set_field(&mut self, name: &str, value: Object) {
+        if let Some(field) = self.fields.get_mut(name) {
+            *field = value;
+        } else {
+            self.fields.insert(Symbol::intern(name), value);
+        }
     }
 }
 
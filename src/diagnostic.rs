@@ -0,0 +1,441 @@
+use std::io::{self, IsTerminal};
+
+use crate::{
+    error::Error,
+    interpreter::Frame,
+    token::{Span, Token, TokenType},
+};
+
+/// The phase of the interpreter that found a problem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Scan,
+    Parse,
+    Resolve,
+    Runtime,
+    /// Problems with the command line or the files it names, before any script runs.
+    Cli,
+}
+
+/// Whether a diagnostic stops the program from running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem in a Lox program, with everything needed to show it to the user.
+///
+/// Codes are grouped by stage: `E00xx` for the scanner, `E01xx` for the parser, `E02xx` for the
+/// resolver, `E03xx` for runtime errors and `E04xx` for the command line. Warnings use `Wxxxx`
+/// codes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub stage: Stage,
+    pub code: &'static str,
+    pub message: String,
+    /// Where on the line the problem is, as in ` at 'x'` or ` at end`.
+    pub location: String,
+    pub line: usize,
+    pub span: Option<Span>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+    /// The calls a runtime error escaped through, outermost first.
+    pub traceback: Vec<Frame>,
+}
+
+impl Diagnostic {
+    pub fn new(stage: Stage, code: &'static str, message: &str, line: usize, span: Span) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            stage,
+            code,
+            message: message.to_string(),
+            location: String::new(),
+            line,
+            // Tokens made up by the interpreter have no place in the source.
+            span: (span.line > 0).then_some(span),
+            notes: vec![],
+            help: None,
+            traceback: vec![],
+        }
+    }
+
+    pub fn at_token(stage: Stage, code: &'static str, token: &Token, message: &str) -> Self {
+        let location = if token.r#type == TokenType::Eof {
+            " at end".to_string()
+        } else {
+            format!(" at '{}'", token.lexeme)
+        };
+        Diagnostic {
+            location,
+            ..Self::new(stage, code, message, token.line, token.span)
+        }
+    }
+
+    /// The diagnostic for an error that escaped the parser or interpreter, if it is one that
+    /// should be shown.
+    pub fn from_error(error: &Error) -> Option<Self> {
+        match error {
+            Error::Parse {
+                token,
+                code,
+                message,
+            } => Some(Self::at_token(Stage::Parse, code, token, message)),
+            Error::Runtime {
+                token,
+                code,
+                message,
+                hint,
+            } => Some(Diagnostic {
+                help: hint.clone(),
+                ..Self::new(Stage::Runtime, code, message, token.line, token.span)
+            }),
+            Error::Io(_) | Error::Compile { .. } | Error::Return { .. } | Error::ShortCircuit => {
+                None
+            }
+        }
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    pub fn with_help(mut self, help: &str) -> Self {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    pub fn with_traceback(mut self, traceback: Vec<Frame>) -> Self {
+        self.traceback = traceback;
+        self
+    }
+
+    /// Renders the classic Lox message, followed by the offending source line with the
+    /// erroneous range underlined, the error code, and any notes and help.
+    pub fn render(&self, source: &str, color: bool) -> String {
+        let paint = |style: &str, text: &str| {
+            if color {
+                format!("\x1b[{}m{}\x1b[0m", style, text)
+            } else {
+                text.to_string()
+            }
+        };
+
+        let (label, style) = match self.severity {
+            Severity::Error => ("Error", BOLD_RED),
+            Severity::Warning => ("Warning", BOLD_YELLOW),
+        };
+        let kind = label.to_lowercase();
+        let mut output = render_traceback(&self.traceback, self.line);
+        match self.stage {
+            Stage::Runtime => {
+                output.push_str(&paint(style, &self.message));
+                output.push_str(&format!("\n[line {}]\n", self.line));
+            }
+            _ => {
+                let header = format!(
+                    "[line {}] {}{}: {}",
+                    self.line, label, self.location, self.message
+                );
+                output.push_str(&paint(style, &header));
+                output.push('\n');
+            }
+        }
+
+        let Some(span) = self.span else {
+            output.push_str(&format!(" --> {}[{}]\n", kind, self.code));
+            return output;
+        };
+
+        let gutter = " ".repeat(span.line.to_string().len());
+        output.push_str(&format!(
+            "{}{} {}[{}] {}:{}\n",
+            gutter,
+            paint(BOLD_BLUE, "-->"),
+            kind,
+            self.code,
+            span.line,
+            span.column
+        ));
+
+        if let Some(text) = source.lines().nth(span.line - 1) {
+            let bar = paint(BOLD_BLUE, "|");
+            let (indent, width) = underline(text, &span);
+            output.push_str(&format!("{} {}\n", gutter, bar));
+            output.push_str(&format!(
+                "{} {} {}\n",
+                paint(BOLD_BLUE, &span.line.to_string()),
+                bar,
+                text
+            ));
+            output.push_str(&format!(
+                "{} {} {}{}\n",
+                gutter,
+                bar,
+                indent,
+                paint(style, &"^".repeat(width))
+            ));
+        }
+
+        for note in &self.notes {
+            output.push_str(&format!("{} = {}: {}\n", gutter, paint(BOLD, "note"), note));
+        }
+        if let Some(help) = &self.help {
+            output.push_str(&format!("{} = {}: {}\n", gutter, paint(BOLD, "help"), help));
+        }
+        output
+    }
+}
+
+impl Severity {
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+impl Stage {
+    fn name(self) -> &'static str {
+        match self {
+            Stage::Scan => "scan",
+            Stage::Parse => "parse",
+            Stage::Resolve => "resolve",
+            Stage::Runtime => "runtime",
+            Stage::Cli => "cli",
+        }
+    }
+}
+
+impl Diagnostic {
+    /// The diagnostic as a single-line JSON object, for editors and other tools. `file` is the
+    /// path of the script the diagnostic is about. Positions are 1-based, as in the text output,
+    /// and `column` and `span` are null when the problem has no place in the source.
+    pub fn to_json(&self, file: &str) -> String {
+        let span = match self.span {
+            Some(span) => format!(
+                "{{\"start\":{},\"end\":{},\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{}}}",
+                span.start, span.end, span.line, span.column, span.end_line, span.end_column
+            ),
+            None => "null".to_string(),
+        };
+        let column = self
+            .span
+            .map_or("null".to_string(), |span| span.column.to_string());
+        let notes: Vec<String> = self.notes.iter().map(|note| json_string(note)).collect();
+        let help = self.help.as_deref().map_or("null".to_string(), json_string);
+        let traceback: Vec<String> = self
+            .traceback
+            .iter()
+            .map(|frame| {
+                format!(
+                    "{{\"function\":{},\"line\":{}}}",
+                    json_string(&frame.function),
+                    frame.line
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"severity\":{},\"stage\":{},\"code\":{},\"message\":{},\"file\":{},\"line\":{},\"column\":{},\"span\":{},\"notes\":[{}],\"help\":{},\"traceback\":[{}]}}",
+            json_string(self.severity.name()),
+            json_string(self.stage.name()),
+            json_string(self.code),
+            json_string(&self.message),
+            json_string(file),
+            self.line,
+            column,
+            span,
+            notes.join(","),
+            help,
+            traceback.join(",")
+        )
+    }
+}
+
+/// Quotes and escapes a string as a JSON string literal.
+fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+const BOLD: &str = "1";
+const BOLD_RED: &str = "1;31";
+const BOLD_YELLOW: &str = "1;33";
+const BOLD_BLUE: &str = "1;34";
+
+/// How many times in a row the same line of a traceback is shown before the rest are counted
+/// instead, so deep recursion stays readable.
+const REPEATED_FRAMES_SHOWN: usize = 3;
+
+/// Lists where each active call was when the error happened, outermost first: the script at the
+/// first call, each function at the call it made next, and the innermost function at the error.
+fn render_traceback(traceback: &[Frame], error_line: usize) -> String {
+    if traceback.is_empty() {
+        return String::new();
+    }
+
+    let functions = std::iter::once("script".to_string()).chain(
+        traceback
+            .iter()
+            .map(|frame| format!("{}()", frame.function)),
+    );
+    let lines = traceback
+        .iter()
+        .map(|frame| frame.line)
+        .chain(std::iter::once(error_line));
+    let entries: Vec<String> = functions
+        .zip(lines)
+        .map(|(function, line)| format!("  [line {}] in {}\n", line, function))
+        .collect();
+
+    let mut output = "Traceback (most recent call last):\n".to_string();
+    let mut entries = entries.iter().peekable();
+    while let Some(entry) = entries.next() {
+        let mut repeats = 1;
+        while entries.next_if_eq(&entry).is_some() {
+            repeats += 1;
+        }
+        for _ in 0..repeats.min(REPEATED_FRAMES_SHOWN) {
+            output.push_str(entry);
+        }
+        if repeats > REPEATED_FRAMES_SHOWN {
+            output.push_str(&format!(
+                "  [Previous line repeated {} more times]\n",
+                repeats - REPEATED_FRAMES_SHOWN
+            ));
+        }
+    }
+    output
+}
+
+/// The whitespace before the underline and its width, both in characters of `text`. Tabs are kept
+/// so the carets line up, and spans running past the line are cut off at its end.
+fn underline(text: &str, span: &Span) -> (String, usize) {
+    let indent: String = text
+        .chars()
+        .take(span.column - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let line_width = text.chars().count();
+    let end = if span.end_line == span.line {
+        span.end_column.min(line_width + 1)
+    } else {
+        line_width + 1
+    };
+    (indent, end.saturating_sub(span.column).max(1))
+}
+
+/// Writes a diagnostic to stderr, in color when stderr is a terminal.
+pub fn emit(diagnostic: &Diagnostic, source: &str) {
+    eprint!("{}", diagnostic.render(source, io::stderr().is_terminal()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+    use insta::assert_snapshot;
+
+    fn token(source: &str, index: usize) -> Token {
+        Scanner::new(source.to_string()).scan_tokens()[index].clone()
+    }
+
+    #[test]
+    fn renders_snippet_with_underline() {
+        let source = "var a = 1;\nprint a +* 2;";
+        let diagnostic = Diagnostic::at_token(
+            Stage::Parse,
+            "E0100",
+            &token(source, 8),
+            "Expect expression.",
+        )
+        .with_note("'*' needs a left operand.")
+        .with_help("remove the extra operator.");
+        assert_snapshot!(diagnostic.render(source, false));
+    }
+
+    #[test]
+    fn underlines_whole_token() {
+        let source = "\tprint undefinedName;";
+        let name = token(source, 1);
+        let diagnostic = Diagnostic::new(
+            Stage::Runtime,
+            "E0301",
+            "Undefined variable 'undefinedName'.",
+            name.line,
+            name.span,
+        );
+        assert_snapshot!(diagnostic.render(source, false));
+    }
+
+    #[test]
+    fn serializes_to_json() {
+        let source = "print \"text\" + nil;";
+        let diagnostic = Diagnostic::at_token(
+            Stage::Runtime,
+            "E0300",
+            &token(source, 2),
+            "Operands must be two numbers or two strings.",
+        )
+        .with_note("the right operand is \"nil\",\n\twhich has no value.")
+        .with_help("check the value\\type.\u{7}")
+        .with_traceback(vec![Frame {
+            function: "main".to_string(),
+            line: 3,
+        }]);
+        assert_snapshot!(diagnostic.to_json("dir/script.lox"));
+        assert_snapshot!(
+            Diagnostic::new(Stage::Scan, "E0001", "Unexpected character.", 1, Span::default())
+                .with_severity(Severity::Warning)
+                .to_json("a.lox"),
+            @r#"{"severity":"warning","stage":"scan","code":"E0001","message":"Unexpected character.","file":"a.lox","line":1,"column":null,"span":null,"notes":[],"help":null,"traceback":[]}"#
+        );
+    }
+
+    #[test]
+    fn collapses_repeated_traceback_lines() {
+        let source = "fun f(n) {\n  return f(n - 1);\n}\nf(10);";
+        let frame = |line| Frame {
+            function: "f".to_string(),
+            line,
+        };
+        let mut traceback = vec![frame(4)];
+        traceback.extend(std::iter::repeat_n(frame(2), 9));
+        let diagnostic = Diagnostic::new(
+            Stage::Runtime,
+            "E0300",
+            "Something went wrong.",
+            2,
+            token(source, 8).span,
+        )
+        .with_traceback(traceback);
+        assert_snapshot!(diagnostic.render(source, false));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::error::Error;
+use crate::suggest::did_you_mean;
 use crate::token::{Object, Token};
 
 use std::cell::RefCell;
@@ -9,7 +10,10 @@ use std::rc::Rc;
 #[derive(Default, Debug, Clone, PartialEq)]
 pub struct Environment {
     pub enclosing: Option<Rc<RefCell<Environment>>>, // Parent
+    /// Globals, found by name. Only the outermost environment, which has no parent, holds them.
     values: HashMap<String, Object>,
+    /// Locals, in the order they are declared, which is the order the resolver numbered them.
+    slots: Vec<Object>,
 }
 
 impl Environment {
@@ -17,6 +21,7 @@ impl Environment {
         Environment {
             enclosing: None,
             values: HashMap::new(),
+            slots: vec![],
         }
     }
 
@@ -24,99 +29,82 @@ impl Environment {
         Environment {
             enclosing: Some(Rc::clone(enclosing)),
             values: HashMap::new(),
+            slots: vec![],
         }
     }
 
+    /// Defines a global by name, or the next local slot in any inner scope.
     pub fn define(&mut self, name: &str, value: Object) {
-        self.values.insert(name.to_string(), value);
+        if self.enclosing.is_some() {
+            self.slots.push(value);
+        } else {
+            self.values.insert(name.to_string(), value);
+        }
     }
 
+    /// Looks up a global.
     pub fn get(&self, name: &Token) -> Result<Object, Error> {
-        let key = &*name.lexeme;
-        if let Some(value) = self.values.get(key) {
-            Ok((*value).clone())
-        } else if let Some(ref enclosing) = self.enclosing {
-            enclosing.borrow().get(name)
-        } else {
-            Err(Error::Runtime {
-                token: name.clone(),
-                message: format!("Undefined variable '{}'.", key),
-            })
-        }
+        self.values
+            .get(&name.lexeme)
+            .cloned()
+            .ok_or_else(|| self.undefined_variable(name))
     }
 
+    /// Overwrites an existing global.
     pub fn assign(&mut self, name: &Token, value: Object) -> Result<(), Error> {
-        let key = &*name.lexeme;
-        if self.values.contains_key(key) {
-            self.values.insert(name.lexeme.clone(), value);
-            Ok(())
-        } else if let Some(ref enclosing) = self.enclosing {
-            enclosing.borrow_mut().assign(name, value)
-        } else {
-            Err(Error::Runtime {
-                token: name.clone(),
-                message: format!("Undefined variable '{}'.", key),
-            })
+        match self.values.get_mut(&name.lexeme) {
+            Some(existing) => {
+                *existing = value;
+                Ok(())
+            }
+            None => Err(self.undefined_variable(name)),
         }
     }
 
-    fn ancestor(&self, distance: usize) -> Rc<RefCell<Environment>> {
-        let rc = self
-            .enclosing
-            .clone()
-            .unwrap_or_else(|| panic!("No enclosing environment at {}", 1));
-        let parent = rc;
-        let mut environment = Rc::clone(&parent);
+    /// Every global's name.
+    pub fn names(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
 
-        for i in 1..distance {
-            let parent = environment
-                .borrow()
-                .enclosing
-                .clone()
-                .unwrap_or_else(|| panic!("No enclosing environment at {}", i));
-            environment = Rc::clone(&parent);
-        }
-        environment
+    /// The error for a global that isn't defined, suggesting the closest global name.
+    pub(crate) fn undefined_variable(&self, name: &Token) -> Error {
+        let names = self.names();
+        Error::runtime(
+            name,
+            "E0301",
+            format!("Undefined variable '{}'.", name.lexeme),
+        )
+        .with_hint(did_you_mean(&name.lexeme, names.iter().map(String::as_str)))
     }
 
-    pub(crate) fn get_at(&self, distance: usize, name: &str) -> Result<Object, Error> {
-        if distance > 0 {
-            Ok(self
-                .ancestor(distance)
-                .borrow()
-                .values
-                .get(name)
-                .unwrap_or_else(|| panic!("Undefined variable '{}'", name))
-                .clone())
-        } else {
-            Ok(self
-                .values
-                .get(name)
-                .unwrap_or_else(|| panic!("Undefined variable '{}'", name))
-                .clone())
+    /// The local in `slot` of the scope `distance` out, or `None` if the resolver and the
+    /// environments disagree about where it lives.
+    pub(crate) fn get_at(&self, distance: usize, slot: usize) -> Option<Object> {
+        match (distance, &self.enclosing) {
+            (0, _) => self.slots.get(slot).cloned(),
+            (_, Some(enclosing)) => enclosing.borrow().get_at(distance - 1, slot),
+            (_, None) => None,
         }
     }
 
-    pub(crate) fn assign_at(
-        &mut self,
-        distance: usize,
-        name: &Token,
-        value: Object,
-    ) -> Result<(), Error> {
-        if distance > 0 {
-            self.ancestor(distance)
-                .borrow_mut()
-                .values
-                .insert(name.lexeme.clone(), value);
-        } else {
-            self.values.insert(name.lexeme.clone(), value);
+    /// Overwrites the local in `slot` of the scope `distance` out, reporting whether it exists.
+    pub(crate) fn assign_at(&mut self, distance: usize, slot: usize, value: Object) -> bool {
+        match (distance, &self.enclosing) {
+            (0, _) => match self.slots.get_mut(slot) {
+                Some(existing) => {
+                    *existing = value;
+                    true
+                }
+                None => false,
+            },
+            (_, Some(enclosing)) => enclosing.borrow_mut().assign_at(distance - 1, slot, value),
+            (_, None) => false,
         }
-        Ok(())
     }
 }
 
 impl fmt::Display for Environment {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "values: {:?}", self.values)
+        write!(f, "values: {:?}, slots: {:?}", self.values, self.slots)
     }
 }
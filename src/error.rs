@@ -2,44 +2,71 @@ use std::convert;
 use std::fmt;
 use std::io;
 
+use crate::diagnostic::Diagnostic;
 use crate::token::Object;
-use crate::token::{Token, TokenType};
+use crate::token::Token;
 
-pub fn error(line: usize, message: &str) {
-    report(line, "", message);
-}
-
-pub fn report(line: usize, loc: &str, message: &str) {
-    eprintln!("[line {}] Error{}: {}", line, loc, message);
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Parse {
+        token: Token,
+        /// The diagnostic code, `E0100` unless the error has one of its own.
+        code: &'static str,
+        message: String,
+    },
+    /// Every problem found before running the program, already in the order to report them.
+    Compile {
+        diagnostics: Vec<Diagnostic>,
+    },
+    Runtime {
+        token: Token,
+        /// The diagnostic code, `E0300` unless the error has one of its own.
+        code: &'static str,
+        message: String,
+        /// A suggestion for fixing the error, such as the name the user probably meant.
+        hint: Option<String>,
+    },
+    Return {
+        value: Object,
+    },
+    ShortCircuit,
 }
 
-pub fn parser_error(token: &Token, message: &str) {
-    if token.r#type == TokenType::Eof {
-        report(token.line, " at end", message);
-    } else {
-        report(token.line, &format!(" at '{}'", token.lexeme), message);
+impl Error {
+    pub fn runtime(token: &Token, code: &'static str, message: impl Into<String>) -> Self {
+        Error::Runtime {
+            token: token.clone(),
+            code,
+            message: message.into(),
+            hint: None,
+        }
     }
-}
 
-#[derive(Debug)]
-pub enum Error {
-    Io(io::Error),
-    Parse { token: Token, message: String },
-    Runtime { token: Token, message: String },
-    Return { value: Object },
+    /// Adds a suggestion to a runtime error. Other errors are returned unchanged.
+    pub fn with_hint(mut self, suggestion: Option<String>) -> Self {
+        if let Error::Runtime { hint, .. } = &mut self {
+            *hint = suggestion;
+        }
+        self
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Error::Io(underlying) => write!(f, "IoError {}", underlying),
-            Error::Parse { token, message } => {
+            Error::Parse { token, message, .. } => {
                 write!(f, "ParseError at token: {}, message: {}", token, message)
             }
-            Error::Runtime { token, message } => {
+            Error::Compile { diagnostics } => {
+                write!(f, "CompileError: {} diagnostics", diagnostics.len())
+            }
+            Error::Runtime { token, message, .. } => {
                 write!(f, "RuntimeError at token: {}, message: {}", token, message)
             }
             Error::Return { value } => write!(f, "Return {:?}", value),
+            Error::ShortCircuit => write!(f, "ShortCircuit"),
         }
     }
 }
@@ -0,0 +1,232 @@
+/// What a Lox script says it should do, read from comments in its source in the format of the
+/// official Lox test suite:
+///
+/// - `// expect: value` for each line the script prints,
+/// - `// expect runtime error: message` on the line that fails at runtime,
+/// - `// Error at 'x': message` on a line with a compile error, or `// [line N] Error ...` to
+///   give the line explicitly. Errors marked `[c line N]` are specific to clox and ignored.
+///
+/// Warnings are not annotated, so they never make a script fail.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Expectations {
+    pub output: Vec<String>,
+    /// Compile errors as their first line is printed, such as `[line 3] Error at 'x': ...`.
+    pub errors: Vec<String>,
+    pub runtime_error: Option<RuntimeError>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+    pub line: usize,
+}
+
+/// How a run of a script ended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    /// The exit code, or `None` if the process was killed by a signal.
+    pub status: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Expectations {
+    pub fn parse(source: &str) -> Self {
+        let mut expectations = Expectations::default();
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            if let Some(output) = after(line, "// expect: ") {
+                expectations.output.push(output.to_string());
+            } else if let Some(message) = after(line, "// expect runtime error: ") {
+                expectations.runtime_error = Some(RuntimeError {
+                    message: message.to_string(),
+                    line: line_number,
+                });
+            } else if let Some(error) = after(line, "// [") {
+                if let Some((position, error)) = error.split_once("] ") {
+                    let position = position.strip_prefix("java ").unwrap_or(position);
+                    if let Some(line) = position.strip_prefix("line ") {
+                        expectations
+                            .errors
+                            .push(format!("[line {}] {}", line, error));
+                    }
+                }
+            } else if let Some(error) = after(line, "// Error") {
+                expectations
+                    .errors
+                    .push(format!("[line {}] Error{}", line_number, error));
+            }
+        }
+        expectations
+    }
+
+    /// The exit code the interpreter should finish with.
+    pub fn status(&self) -> i32 {
+        if !self.errors.is_empty() {
+            65
+        } else if self.runtime_error.is_some() {
+            70
+        } else {
+            0
+        }
+    }
+
+    /// Every way `outcome` differs from what was expected, each ready to print. The compile
+    /// errors in stderr must be exactly the expected ones, while anything else there, such as
+    /// warnings, is allowed.
+    pub fn check(&self, outcome: &Outcome) -> Vec<String> {
+        let mut problems = vec![];
+
+        let status = self.status();
+        if outcome.status != Some(status) {
+            let actual = outcome
+                .status
+                .map_or("no exit code".to_string(), |code| code.to_string());
+            problems.push(format!("expected exit code {} but got {}", status, actual));
+        }
+
+        let stdout: Vec<&str> = outcome.stdout.lines().collect();
+        let expected: Vec<&str> = self.output.iter().map(String::as_str).collect();
+        if stdout != expected {
+            let mut problem = "output differs (- expected, + actual):".to_string();
+            for line in diff(&expected, &stdout) {
+                problem.push_str("\n  ");
+                problem.push_str(&line);
+            }
+            problems.push(problem);
+        }
+
+        let stderr: Vec<&str> = outcome.stderr.lines().collect();
+        let mut unexpected: Vec<&str> = stderr
+            .iter()
+            .copied()
+            .filter(|line| is_compile_error(line))
+            .collect();
+        for error in &self.errors {
+            match unexpected.iter().position(|line| line == error) {
+                Some(index) => {
+                    unexpected.remove(index);
+                }
+                None => problems.push(format!("missing error: {}", error)),
+            }
+        }
+        for error in unexpected {
+            problems.push(format!("unexpected error: {}", error));
+        }
+        if let Some(RuntimeError { message, line }) = &self.runtime_error {
+            let location = format!("[line {}]", line);
+            let reported = stderr
+                .windows(2)
+                .any(|pair| pair[0] == message && pair[1] == location);
+            if !reported {
+                problems.push(format!("missing runtime error: {} {}", message, location));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Whether a line of stderr is the first line of a compile error, such as
+/// `[line 3] Error at 'x': ...`, rather than a warning or part of a diagnostic's rendering.
+fn is_compile_error(line: &str) -> bool {
+    line.strip_prefix("[line ")
+        .and_then(|rest| rest.split_once("] "))
+        .is_some_and(|(number, message)| {
+            number.bytes().all(|byte| byte.is_ascii_digit()) && message.starts_with("Error")
+        })
+}
+
+/// The text after `marker` in `line`, if it has one.
+fn after<'a>(line: &'a str, marker: &str) -> Option<&'a str> {
+    line.find(marker).map(|start| &line[start + marker.len()..])
+}
+
+/// A line diff turning `expected` into `actual`, with unchanged lines prefixed by two spaces and
+/// removed and added lines by `- ` and `+ `.
+fn diff(expected: &[&str], actual: &[&str]) -> Vec<String> {
+    // common[i][j] is the length of the longest common subsequence of expected[i..] and actual[j..].
+    let mut common = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = vec![];
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            lines.push(format!("  {}", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j == actual.len() || (i < expected.len() && common[i + 1][j] >= common[i][j + 1])
+        {
+            lines.push(format!("- {}", expected[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", actual[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::assert_debug_snapshot;
+
+    fn outcome(status: i32, stdout: &str, stderr: &str) -> Outcome {
+        Outcome {
+            status: Some(status),
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_annotations() {
+        let source = "print 1; // expect: 1\n\
+                      var a = ; // Error at ';': Expect expression.\n\
+                      // [line 7] Error at end: Expect '}' after block.\n\
+                      // [java line 8] Error at 'x': Java only.\n\
+                      // [c line 9] Error at 'x': C only.\n\
+                      a(); // expect runtime error: Can only call functions and classes.";
+        assert_debug_snapshot!(Expectations::parse(source));
+    }
+
+    #[test]
+    fn passes_matching_run() {
+        let expectations = Expectations::parse(
+            "print 1; // expect: 1\nnil(); // expect runtime error: Can only call functions and classes.",
+        );
+        let stderr =
+            "[line 1] Warning at 'x': Unused.\nCan only call functions and classes.\n[line 2]\n";
+        assert!(expectations.check(&outcome(70, "1\n", stderr)).is_empty());
+    }
+
+    #[test]
+    fn reports_every_difference() {
+        let expectations = Expectations::parse(
+            "// expect: a\n// expect: b\n// expect: c\nvar = 1; // Error at '=': Expect variable name.",
+        );
+        assert_debug_snapshot!(expectations.check(&outcome(0, "a\nc\nd\n", "")));
+    }
+
+    #[test]
+    fn reports_unexpected_errors() {
+        let expectations = Expectations::parse("var = 1; // Error at '=': Expect variable name.");
+        let stderr = "[line 1] Error at '=': Expect variable name.\n\
+                      [line 1] Warning at 'x': Unused.\n\
+                      [line 2] Error at end: Expect ';' after value.\n";
+        assert_eq!(
+            expectations.check(&outcome(65, "", stderr)),
+            ["unexpected error: [line 2] Error at end: Expect ';' after value."]
+        );
+    }
+}
@@ -1,57 +1,109 @@
 use crate::{
     error::Error,
-    token::{Object, Token},
+    token::{Object, Span, Token},
 };
 
+/// Identifies one variable, `this` or `super` expression, so the resolver can record where its
+/// binding lives even when the same name appears more than once on a line. The parser numbers
+/// them in order, unique within everything it parses.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprId(pub usize);
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Assign {
+        id: ExprId,
         name: Token,
         value: Box<Expr>,
+        span: Span,
+    },
+    AssignTuple {
+        /// One for each name.
+        ids: Vec<ExprId>,
+        names: Vec<Token>,
+        equals: Token,
+        value: Box<Expr>,
+        span: Span,
     },
     Binary {
         left: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
+        span: Span,
     },
     Get {
         object: Box<Expr>,
         name: Token,
+        span: Span,
+    },
+    OptionalGet {
+        object: Box<Expr>,
+        name: Token,
+        span: Span,
+    },
+    OptionalChain {
+        expr: Box<Expr>,
+        span: Span,
     },
     Grouping {
         expr: Box<Expr>,
+        span: Span,
+    },
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        span: Span,
     },
     Literal {
         value: Object,
+        span: Span,
+    },
+    Tuple {
+        elements: Vec<Expr>,
+        span: Span,
     },
     Unary {
         operator: Token,
         right: Box<Expr>,
+        span: Span,
     },
     Variable {
+        id: ExprId,
         name: Token,
+        span: Span,
     },
     Logical {
         left: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
+        span: Span,
     },
     Call {
         callee: Box<Expr>,
         paren: Token,
         arguments: Vec<Expr>,
+        /// Set when the call comes from `value |> target`, whose value is the first argument and
+        /// is evaluated before the callee.
+        piped: bool,
+        span: Span,
     },
     Set {
         object: Box<Expr>,
         name: Token,
         value: Box<Expr>,
+        span: Span,
     },
     Super {
+        id: ExprId,
         keyword: Token,
         method: Token,
+        span: Span,
     },
     This {
+        id: ExprId,
         keyword: Token,
+        span: Span,
     },
 }
 
@@ -61,7 +113,7 @@ pub mod expr {
         token::{Object, Token},
     };
 
-    use super::Expr;
+    use super::{Expr, ExprId};
 
     pub trait Visitor<R> {
         fn visit_binary_expr(
@@ -71,10 +123,25 @@ pub mod expr {
             right: &Expr,
         ) -> Result<R, Error>;
         fn visit_grouping_expr(&mut self, expr: &Expr) -> Result<R, Error>;
+        fn visit_index_expr(
+            &mut self,
+            object: &Expr,
+            bracket: &Token,
+            index: &Expr,
+        ) -> Result<R, Error>;
         fn visit_literal_expr(&self, value: &Object) -> Result<R, Error>;
+        fn visit_tuple_expr(&mut self, elements: &[Expr]) -> Result<R, Error>;
         fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> Result<R, Error>;
-        fn visit_variable_expr(&mut self, name: &Token) -> Result<R, Error>;
-        fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> Result<R, Error>;
+        fn visit_variable_expr(&mut self, id: ExprId, name: &Token) -> Result<R, Error>;
+        fn visit_assign_expr(&mut self, id: ExprId, name: &Token, value: &Expr)
+            -> Result<R, Error>;
+        fn visit_assign_tuple_expr(
+            &mut self,
+            ids: &[ExprId],
+            names: &[Token],
+            equals: &Token,
+            value: &Expr,
+        ) -> Result<R, Error>;
         fn visit_logical_expr(
             &mut self,
             left: &Expr,
@@ -86,46 +153,107 @@ pub mod expr {
             callee: &Expr,
             paren: &Token,
             arguments: &[Expr],
+            piped: bool,
         ) -> Result<R, Error>;
         fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> Result<R, Error>;
+        fn visit_optional_get_expr(&mut self, object: &Expr, name: &Token) -> Result<R, Error>;
+        fn visit_optional_chain_expr(&mut self, expr: &Expr) -> Result<R, Error>;
         fn visit_set_expr(&mut self, object: &Expr, name: &Token, value: &Expr)
             -> Result<R, Error>;
-        fn visit_this_expr(&mut self, keyword: &Token) -> Result<R, Error>;
-        fn visit_super_expr(&mut self, keyword: &Token, method: &Token) -> Result<R, Error>;
+        fn visit_this_expr(&mut self, id: ExprId, keyword: &Token) -> Result<R, Error>;
+        fn visit_super_expr(
+            &mut self,
+            id: ExprId,
+            keyword: &Token,
+            method: &Token,
+        ) -> Result<R, Error>;
     }
 }
 
 impl Expr {
+    /// The source range of the whole expression, from its first token to its last.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Assign { span, .. }
+            | Expr::AssignTuple { span, .. }
+            | Expr::Binary { span, .. }
+            | Expr::Get { span, .. }
+            | Expr::OptionalGet { span, .. }
+            | Expr::OptionalChain { span, .. }
+            | Expr::Grouping { span, .. }
+            | Expr::Index { span, .. }
+            | Expr::Literal { span, .. }
+            | Expr::Tuple { span, .. }
+            | Expr::Unary { span, .. }
+            | Expr::Variable { span, .. }
+            | Expr::Logical { span, .. }
+            | Expr::Call { span, .. }
+            | Expr::Set { span, .. }
+            | Expr::Super { span, .. }
+            | Expr::This { span, .. } => *span,
+        }
+    }
+
     pub fn accept<R>(&self, visitor: &mut dyn expr::Visitor<R>) -> Result<R, Error> {
         match self {
-            Expr::Assign { name, value } => visitor.visit_assign_expr(name, value),
+            Expr::Assign {
+                id, name, value, ..
+            } => visitor.visit_assign_expr(*id, name, value),
+            Expr::AssignTuple {
+                ids,
+                names,
+                equals,
+                value,
+                ..
+            } => visitor.visit_assign_tuple_expr(ids, names, equals, value),
             Expr::Binary {
                 left,
                 operator,
                 right,
+                ..
             } => visitor.visit_binary_expr(left, operator, right),
-            Expr::Grouping { expr } => visitor.visit_grouping_expr(expr),
-            Expr::Literal { value } => visitor.visit_literal_expr(value),
-            Expr::Unary { operator, right } => visitor.visit_unary_expr(operator, right),
-            Expr::Variable { name } => visitor.visit_variable_expr(name),
+            Expr::Grouping { expr, .. } => visitor.visit_grouping_expr(expr),
+            Expr::Index {
+                object,
+                bracket,
+                index,
+                ..
+            } => visitor.visit_index_expr(object, bracket, index),
+            Expr::Literal { value, .. } => visitor.visit_literal_expr(value),
+            Expr::Tuple { elements, .. } => visitor.visit_tuple_expr(elements),
+            Expr::Unary {
+                operator, right, ..
+            } => visitor.visit_unary_expr(operator, right),
+            Expr::Variable { id, name, .. } => visitor.visit_variable_expr(*id, name),
             Expr::Logical {
                 left,
                 operator,
                 right,
+                ..
             } => visitor.visit_logical_expr(left, operator, right),
             Expr::Call {
                 callee,
                 paren,
                 arguments,
-            } => visitor.visit_call_expr(callee, paren, arguments),
-            Expr::Get { object, name } => visitor.visit_get_expr(object, name),
+                piped,
+                ..
+            } => visitor.visit_call_expr(callee, paren, arguments, *piped),
+            Expr::Get { object, name, .. } => visitor.visit_get_expr(object, name),
+            Expr::OptionalGet { object, name, .. } => visitor.visit_optional_get_expr(object, name),
+            Expr::OptionalChain { expr, .. } => visitor.visit_optional_chain_expr(expr),
             Expr::Set {
                 object,
                 name,
                 value,
+                ..
             } => visitor.visit_set_expr(object, name, value),
-            Expr::This { keyword } => visitor.visit_this_expr(keyword),
-            Expr::Super { keyword, method } => visitor.visit_super_expr(keyword, method),
+            Expr::This { id, keyword, .. } => visitor.visit_this_expr(*id, keyword),
+            Expr::Super {
+                id,
+                keyword,
+                method,
+                ..
+            } => visitor.visit_super_expr(*id, keyword, method),
         }
     }
 }
@@ -164,22 +292,52 @@ impl expr::Visitor<String> for AstPrinter {
         self.parenthesize("group".to_string(), &[expr])
     }
 
+    fn visit_index_expr(
+        &mut self,
+        object: &Expr,
+        _bracket: &Token,
+        index: &Expr,
+    ) -> Result<String, Error> {
+        self.parenthesize("index".to_string(), &[object, index])
+    }
+
     fn visit_literal_expr(&self, value: &Object) -> Result<String, Error> {
         Ok(value.to_string())
     }
 
+    fn visit_tuple_expr(&mut self, elements: &[Expr]) -> Result<String, Error> {
+        let elements: Vec<&Expr> = elements.iter().collect();
+        self.parenthesize("tuple".to_string(), &elements)
+    }
+
     fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> Result<String, Error> {
         self.parenthesize(operator.lexeme.clone(), &[right])
     }
 
-    fn visit_variable_expr(&mut self, name: &Token) -> Result<String, Error> {
+    fn visit_variable_expr(&mut self, _id: ExprId, name: &Token) -> Result<String, Error> {
         Ok(name.lexeme.clone())
     }
 
-    fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> Result<String, Error> {
+    fn visit_assign_expr(
+        &mut self,
+        _id: ExprId,
+        name: &Token,
+        value: &Expr,
+    ) -> Result<String, Error> {
         self.parenthesize(name.lexeme.clone(), &[value])
     }
 
+    fn visit_assign_tuple_expr(
+        &mut self,
+        _ids: &[ExprId],
+        names: &[Token],
+        _equals: &Token,
+        value: &Expr,
+    ) -> Result<String, Error> {
+        let names: Vec<&str> = names.iter().map(|name| name.lexeme.as_str()).collect();
+        self.parenthesize(format!("({})", names.join(" ")), &[value])
+    }
+
     fn visit_logical_expr(
         &mut self,
         left: &Expr,
@@ -193,6 +351,7 @@ impl expr::Visitor<String> for AstPrinter {
         callee: &Expr,
         paren: &Token,
         arguments: &[Expr],
+        _piped: bool,
     ) -> Result<String, Error> {
         let mut aggregated = vec![callee];
         aggregated.extend(arguments.iter());
@@ -203,6 +362,14 @@ impl expr::Visitor<String> for AstPrinter {
         self.parenthesize(name.lexeme.clone(), &[object])
     }
 
+    fn visit_optional_get_expr(&mut self, object: &Expr, name: &Token) -> Result<String, Error> {
+        self.parenthesize(format!("?.{}", name.lexeme), &[object])
+    }
+
+    fn visit_optional_chain_expr(&mut self, expr: &Expr) -> Result<String, Error> {
+        self.parenthesize("chain".to_string(), &[expr])
+    }
+
     fn visit_set_expr(
         &mut self,
         object: &Expr,
@@ -212,11 +379,16 @@ impl expr::Visitor<String> for AstPrinter {
         self.parenthesize(name.lexeme.clone(), &[object, value])
     }
 
-    fn visit_this_expr(&mut self, _keyword: &Token) -> Result<String, Error> {
+    fn visit_this_expr(&mut self, _id: ExprId, _keyword: &Token) -> Result<String, Error> {
         Ok("this".to_string())
     }
 
-    fn visit_super_expr(&mut self, _keyword: &Token, _method: &Token) -> Result<String, Error> {
+    fn visit_super_expr(
+        &mut self,
+        _id: ExprId,
+        _keyword: &Token,
+        _method: &Token,
+    ) -> Result<String, Error> {
         Ok("super".to_string())
     }
 }
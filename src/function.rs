@@ -9,11 +9,33 @@ use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
 
+pub type NativeFn = fn(&[Object]) -> Result<Object, NativeError>;
+
+/// Why a native function failed, reported as a runtime error at the call.
 #[derive(Debug, Clone, PartialEq)]
+pub struct NativeError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl NativeError {
+    pub fn new(code: &'static str, message: String) -> Self {
+        NativeError { code, message }
+    }
+}
+
+/// Most native errors are about their arguments and have no code of their own.
+impl From<String> for NativeError {
+    fn from(message: String) -> Self {
+        NativeError::new("E0300", message)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum Function {
     Native {
         arity: usize,
-        body: Box<fn(&[Object]) -> Object>,
+        body: Box<NativeFn>,
     },
 
     User {
@@ -29,10 +51,13 @@ impl Function {
     pub fn call(
         &self,
         interpreter: &mut Interpreter,
+        paren: &Token,
         arguments: &[Object],
     ) -> Result<Object, Error> {
         match self {
-            Function::Native { body, .. } => Ok(body(arguments)),
+            Function::Native { body, .. } => {
+                body(arguments).map_err(|error| Error::runtime(paren, error.code, error.message))
+            }
             Function::User {
                 params,
                 body,
@@ -46,28 +71,18 @@ impl Function {
                         .borrow_mut()
                         .define(&param.lexeme, argument.clone());
                 }
-                match interpreter.execute_block(body, environment) {
-                    Err(Error::Return { value }) => {
-                        if *is_initializer {
-                            Ok(closure
-                                .borrow()
-                                .get_at(0, "this")
-                                .expect("Initializer should return 'this'."))
-                        } else {
-                            Ok(value)
-                        }
-                    }
-                    Err(other) => Err(other),
-                    Ok(..) => {
-                        if *is_initializer {
-                            Ok(closure
-                                .borrow()
-                                .get_at(0, "this")
-                                .expect("Initializer should return 'this'."))
-                        } else {
-                            Ok(Object::Nil)
-                        }
-                    }
+                let value = match interpreter.execute_block(body, environment) {
+                    Err(Error::Return { value }) => value,
+                    Err(other) => return Err(other),
+                    Ok(..) => Object::Nil,
+                };
+                if *is_initializer {
+                    // `this` is the only local in the scope a method is bound in.
+                    closure.borrow().get_at(0, 0).ok_or_else(|| {
+                        Error::runtime(paren, "E0300", "Initializer is not bound to an instance.")
+                    })
+                } else {
+                    Ok(value)
                 }
             }
         }
@@ -82,7 +97,7 @@ impl Function {
 
     pub fn bind(&self, instance: Object) -> Self {
         match self {
-            Function::Native { .. } => unreachable!(),
+            Function::Native { .. } => self.clone(),
             Function::User {
                 name,
                 params,
@@ -104,10 +119,35 @@ impl Function {
     }
 }
 
+/// Functions are equal when they are the same declaration closed over the same environment, so
+/// each time a method is bound it makes a new, distinct function.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Function::Native { body: left, .. }, Function::Native { body: right, .. }) => {
+                std::ptr::fn_addr_eq(**left, **right)
+            }
+            (
+                Function::User {
+                    name: left,
+                    closure: left_closure,
+                    ..
+                },
+                Function::User {
+                    name: right,
+                    closure: right_closure,
+                    ..
+                },
+            ) => left.span == right.span && Rc::ptr_eq(left_closure, right_closure),
+            _ => false,
+        }
+    }
+}
+
 impl fmt::Display for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Function::Native { .. } => write!(f, "<native function>"),
+            Function::Native { .. } => write!(f, "<native fn>"),
             Function::User { name, .. } => write!(f, "<fn {}>", name.lexeme),
         }
     }
@@ -1,17 +1,16 @@
-use std::{
-    cell::RefCell,
-    collections::HashMap,
-    rc::Rc,
-    time::{SystemTime, UNIX_EPOCH},
-};
+use std::{cell::RefCell, collections::HashMap, mem, rc::Rc};
 
 use crate::{
+    bigint::BigInt,
     class::{Class, Instance},
     environment::Environment,
     error::Error,
-    expr::{expr, Expr},
+    expr::{expr, Expr, ExprId},
     function::Function,
+    native::define_natives,
+    sequence::{self, Range},
     stmt::{stmt, Stmt},
+    suggest::did_you_mean,
     token::{Object, Token, TokenType},
 };
 
@@ -19,28 +18,53 @@ use crate::{
 pub struct Interpreter {
     pub globals: Rc<RefCell<Environment>>,
     environment: Rc<RefCell<Environment>>,
-    locals: HashMap<Token, usize>,
+    /// Where each resolved local lives, indexed by the ID of the expression that uses it, since
+    /// the same name can be used at different depths on one line. IDs are dense, so a vector
+    /// is cheaper to look up than a map on every variable access.
+    locals: Vec<Option<Slot>>,
+    /// The locals in scope where each unresolved name was used, for suggesting one of them if
+    /// it turns out not to be a global either.
+    visible_locals: HashMap<ExprId, Vec<String>>,
+    call_stack: Vec<Frame>,
+    traceback: Vec<Frame>,
+    /// How many more loop iterations and calls the program may make, if it is limited.
+    steps_left: Option<usize>,
+    /// Whether `print` statements are discarded instead of written to stdout.
+    quiet: bool,
+}
+
+/// How many user function calls may be active at once before the program fails with a stack
+/// overflow error.
+const MAX_CALL_DEPTH: usize = 4096;
+
+/// Where the resolver found a local: how many scopes out from its use, and its slot there.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Slot {
+    pub depth: usize,
+    pub index: usize,
+}
+
+/// A call to a user function that has not returned yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub function: String,
+    /// The line of the call, taken from its closing parenthesis.
+    pub line: usize,
 }
 
 impl Default for Interpreter {
     fn default() -> Self {
         let globals = Rc::new(RefCell::new(Environment::new()));
-        let clock: Object = Object::Callable(Function::Native {
-            arity: 0,
-            body: Box::new(|_: &[Object]| {
-                Object::Number(
-                    SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .expect("Could not retrieve time.")
-                        .as_millis() as f64,
-                )
-            }),
-        });
-        globals.borrow_mut().define("clock", clock);
+        define_natives(&mut globals.borrow_mut());
         Interpreter {
             globals: Rc::clone(&globals),
             environment: Rc::clone(&globals),
-            locals: HashMap::new(),
+            locals: vec![],
+            visible_locals: HashMap::new(),
+            call_stack: vec![],
+            traceback: vec![],
+            steps_left: None,
+            quiet: false,
         }
     }
 }
@@ -50,7 +74,20 @@ impl Interpreter {
         Self::default()
     }
 
-    pub fn interpret(&mut self, statements: &Vec<Stmt>) -> Result<(), Error> {
+    /// Stops the program with a runtime error once it has made `steps` loop iterations and calls
+    /// in total, for running code that might never finish.
+    pub fn with_step_limit(mut self, steps: usize) -> Self {
+        self.steps_left = Some(steps);
+        self
+    }
+
+    /// Discards what the program prints, for running code whose output nobody reads.
+    pub fn without_output(mut self) -> Self {
+        self.quiet = true;
+        self
+    }
+
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), Error> {
         for statement in statements {
             self.execute(statement)?;
         }
@@ -64,6 +101,65 @@ impl Interpreter {
         Ok(())
     }
 
+    /// The calls that were active when the last runtime error was raised, outermost first.
+    /// Taking it clears it, ready for the next error.
+    pub fn take_traceback(&mut self) -> Vec<Frame> {
+        mem::take(&mut self.traceback)
+    }
+
+    /// Calls the global `name` with no arguments, reporting any error at the name.
+    pub fn call_global(&mut self, name: &Token) -> Result<Object, Error> {
+        let callee = self.globals.borrow().get(name)?;
+        match callee {
+            Object::Callable(function) if function.arity() == 0 => {
+                self.call_function(&function, name, &[])
+            }
+            _ => Err(Error::runtime(
+                name,
+                "E0304",
+                "Can only call functions and classes.",
+            )),
+        }
+    }
+
+    fn call_function(
+        &mut self,
+        function: &Function,
+        paren: &Token,
+        arguments: &[Object],
+    ) -> Result<Object, Error> {
+        self.step(paren)?;
+        let Function::User { name, .. } = function else {
+            return function.call(self, paren, arguments);
+        };
+        if self.call_stack.len() >= MAX_CALL_DEPTH {
+            return Err(Error::runtime(paren, "E0300", "Stack overflow."));
+        }
+
+        self.call_stack.push(Frame {
+            function: name.lexeme.clone(),
+            line: paren.line,
+        });
+        let result = function.call(self, paren, arguments);
+        // The innermost frame sees the error first, while the whole stack is still there.
+        if let (Err(Error::Runtime { .. }), true) = (&result, self.traceback.is_empty()) {
+            self.traceback = self.call_stack.clone();
+        }
+        self.call_stack.pop();
+        result
+    }
+
+    fn step(&mut self, token: &Token) -> Result<(), Error> {
+        match &mut self.steps_left {
+            Some(0) => Err(Error::runtime(token, "E0300", "Step limit exceeded.")),
+            Some(steps) => {
+                *steps -= 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
     fn evaluate(&mut self, expression: &Expr) -> Result<Object, Error> {
         expression.accept(self)
     }
@@ -72,18 +168,59 @@ impl Interpreter {
         statement.accept(self)
     }
 
-    pub(crate) fn resolve(&mut self, name: &Token, depth: usize) {
-        self.locals.insert(name.clone(), depth);
+    pub(crate) fn resolve(&mut self, id: ExprId, slot: Slot) {
+        if self.locals.len() <= id.0 {
+            self.locals.resize(id.0 + 1, None);
+        }
+        self.locals[id.0] = Some(slot);
+    }
+
+    pub(crate) fn resolve_global(&mut self, id: ExprId, visible_locals: Vec<String>) {
+        self.visible_locals.insert(id, visible_locals);
+    }
+
+    fn local(&self, id: ExprId) -> Option<Slot> {
+        self.locals.get(id.0).copied().flatten()
+    }
+
+    fn look_up_variable(&self, id: ExprId, name: &Token) -> Result<Object, Error> {
+        let value = match self.local(id) {
+            Some(Slot { depth, index }) => self.environment.borrow().get_at(depth, index),
+            None => self.globals.borrow().get(name).ok(),
+        };
+        value.ok_or_else(|| self.undefined_variable(id, name))
     }
 
-    fn look_up_variable(&self, name: &Token) -> Result<Object, Error> {
-        if let Some(distance) = self.locals.get(name) {
-            self.environment.borrow().get_at(*distance, &name.lexeme)
+    fn assign_variable(&mut self, id: ExprId, name: &Token, value: Object) -> Result<(), Error> {
+        let assigned = match self.local(id) {
+            Some(Slot { depth, index }) => {
+                self.environment.borrow_mut().assign_at(depth, index, value)
+            }
+            None => self.globals.borrow_mut().assign(name, value).is_ok(),
+        };
+        if assigned {
+            Ok(())
         } else {
-            self.globals.borrow().get(name)
+            Err(self.undefined_variable(id, name))
         }
     }
 
+    /// The error for a name that isn't defined, suggesting the closest local or global that is
+    /// visible where it was used.
+    fn undefined_variable(&self, id: ExprId, name: &Token) -> Error {
+        let globals = self.globals.borrow().names();
+        let locals = self.visible_locals.get(&id).into_iter().flatten();
+        Error::runtime(
+            name,
+            "E0301",
+            format!("Undefined variable '{}'.", name.lexeme),
+        )
+        .with_hint(did_you_mean(
+            &name.lexeme,
+            locals.chain(&globals).map(String::as_str),
+        ))
+    }
+
     pub(crate) fn execute_block(
         &mut self,
         statements: &[Stmt],
@@ -102,6 +239,56 @@ impl Interpreter {
         result
     }
 
+    fn integer_result(&self, result: Option<i64>, operator: &Token) -> Result<Object, Error> {
+        result
+            .map(Object::Integer)
+            .ok_or_else(|| Error::runtime(operator, "E0305", "Integer overflow."))
+    }
+
+    /// Arithmetic where either operand is a BigInt. Integers are promoted, but floats are rejected
+    /// rather than silently losing precision, and division truncates toward zero.
+    fn bigint_binary(
+        &self,
+        left: &Object,
+        operator: &Token,
+        right: &Object,
+    ) -> Result<Object, Error> {
+        let (left_big, right_big) = match (left, right) {
+            (Object::Number(_), _) | (_, Object::Number(_)) => {
+                return Err(Error::runtime(
+                    operator,
+                    "E0300",
+                    "Cannot mix BigInt and float operands.",
+                ))
+            }
+            (Object::BigInt(_) | Object::Integer(_), Object::BigInt(_) | Object::Integer(_)) => {
+                (to_bigint(left), to_bigint(right))
+            }
+            _ => return self.runtime_error(left, operator, right),
+        };
+
+        match operator.r#type {
+            TokenType::Minus => Ok(Object::BigInt(&left_big - &right_big)),
+            TokenType::Slash => left_big
+                .checked_div(&right_big)
+                .map(Object::BigInt)
+                .ok_or_else(|| {
+                    Error::runtime(
+                        operator,
+                        "E0308",
+                        format!("Zero division error. Tried to divide {} by 0.", left),
+                    )
+                }),
+            TokenType::Star => Ok(Object::BigInt(&left_big * &right_big)),
+            TokenType::Plus => Ok(Object::BigInt(&left_big + &right_big)),
+            TokenType::Greater => Ok(Object::Bool(left_big > right_big)),
+            TokenType::GreaterEqual => Ok(Object::Bool(left_big >= right_big)),
+            TokenType::Less => Ok(Object::Bool(left_big < right_big)),
+            TokenType::LessEqual => Ok(Object::Bool(left_big <= right_big)),
+            _ => self.runtime_error(left, operator, right),
+        }
+    }
+
     fn runtime_error(
         &self,
         left: &Object,
@@ -115,18 +302,10 @@ impl Interpreter {
             | TokenType::Greater
             | TokenType::GreaterEqual
             | TokenType::Less
-            | TokenType::LessEqual => {
-                format!(
-                    "Operands must be numbers. Was: {} {} {}",
-                    left, operator, right
-                )
-            }
-            TokenType::Plus => {
-                format!(
-                    "Operands must be two numbers or two strings. Was: {} {} {}",
-                    left, operator, right
-                )
-            }
+            | TokenType::LessEqual
+            | TokenType::DotDot
+            | TokenType::DotDotEqual => "Operands must be numbers.".to_string(),
+            TokenType::Plus => "Operands must be two numbers or two strings.".to_string(),
             _ => {
                 format!(
                     "Invalid expression error. Was: {} {} {}",
@@ -134,10 +313,51 @@ impl Interpreter {
                 )
             }
         };
-        Err(Error::Runtime {
-            token: operator.clone(),
-            message,
-        })
+        Err(Error::runtime(operator, "E0300", message))
+    }
+}
+
+fn is_arithmetic(operator: &TokenType) -> bool {
+    matches!(
+        operator,
+        TokenType::Minus
+            | TokenType::Slash
+            | TokenType::Star
+            | TokenType::Plus
+            | TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual
+    )
+}
+
+/// Promotes an integer operand of BigInt arithmetic.
+fn to_bigint(value: &Object) -> BigInt {
+    match value {
+        Object::BigInt(big) => big.clone(),
+        Object::Integer(int) => BigInt::from(*int),
+        _ => BigInt::default(),
+    }
+}
+
+/// Unpacks a tuple into exactly `arity` values, reporting mismatches at `token`.
+fn destructure(value: &Object, arity: usize, token: &Token) -> Result<Rc<Vec<Object>>, Error> {
+    match value {
+        Object::Tuple(elements) if elements.len() == arity => Ok(Rc::clone(elements)),
+        Object::Tuple(elements) => Err(Error::runtime(
+            token,
+            "E0309",
+            format!(
+                "Expected {} values to destructure but got {}.",
+                arity,
+                elements.len()
+            ),
+        )),
+        _ => Err(Error::runtime(
+            token,
+            "E0309",
+            "Can only destructure tuples.",
+        )),
     }
 }
 
@@ -148,50 +368,90 @@ impl expr::Visitor<Object> for Interpreter {
         operator: &Token,
         right: &Expr,
     ) -> Result<Object, Error> {
-        let left = self
-            .evaluate(left)
-            .unwrap_or_else(|_| panic!("Could not evaluate left expr: {:?}", left));
-        let right = self
-            .evaluate(right)
-            .unwrap_or_else(|_| panic!("Could not evaluate right expr: {:?}", right));
+        let left = self.evaluate(left)?;
+        let right = self.evaluate(right)?;
 
         match (&left, &operator.r#type, &right) {
-            (Object::Number(left_num), TokenType::Minus, Object::Number(right_num)) => {
-                Ok(Object::Number(left_num - right_num))
+            (Object::Integer(left_int), TokenType::Minus, Object::Integer(right_int)) => {
+                self.integer_result(left_int.checked_sub(*right_int), operator)
             }
-            (Object::Number(left_num), TokenType::Slash, Object::Number(0.0)) => {
-                Err(Error::Runtime {
-                    token: operator.clone(),
-                    message: format!("Zero division error. Tried to divide {} by 0.", left_num),
-                })
+            (Object::Integer(left_int), TokenType::Slash, Object::Integer(right_int))
+                if *right_int != 0 && left_int.wrapping_rem(*right_int) == 0 =>
+            {
+                self.integer_result(left_int.checked_div(*right_int), operator)
             }
-            (Object::Number(left_num), TokenType::Slash, Object::Number(right_num)) => {
-                Ok(Object::Number(left_num / right_num))
+            (Object::Integer(left_int), TokenType::Star, Object::Integer(right_int)) => {
+                self.integer_result(left_int.checked_mul(*right_int), operator)
             }
-            (Object::Number(left_num), TokenType::Star, Object::Number(right_num)) => {
-                Ok(Object::Number(left_num * right_num))
+            (Object::Integer(left_int), TokenType::Plus, Object::Integer(right_int)) => {
+                self.integer_result(left_int.checked_add(*right_int), operator)
             }
-            (Object::Number(left_num), TokenType::Plus, Object::Number(right_num)) => {
-                Ok(Object::Number(left_num + right_num))
+            (Object::Integer(left_int), TokenType::Greater, Object::Integer(right_int)) => {
+                Ok(Object::Bool(left_int > right_int))
             }
-            (Object::String(left_str), TokenType::Plus, Object::String(right_str)) => {
-                Ok(Object::String(left_str.to_owned() + right_str))
+            (Object::Integer(left_int), TokenType::GreaterEqual, Object::Integer(right_int)) => {
+                Ok(Object::Bool(left_int >= right_int))
             }
-            (Object::Number(left_num), TokenType::Greater, Object::Number(right_num)) => {
-                Ok(Object::Bool(left_num > right_num))
+            (Object::Integer(left_int), TokenType::Less, Object::Integer(right_int)) => {
+                Ok(Object::Bool(left_int < right_int))
             }
-            (Object::Number(left_num), TokenType::GreaterEqual, Object::Number(right_num)) => {
-                Ok(Object::Bool(left_num >= right_num))
+            (Object::Integer(left_int), TokenType::LessEqual, Object::Integer(right_int)) => {
+                Ok(Object::Bool(left_int <= right_int))
             }
-            (Object::Number(left_num), TokenType::Less, Object::Number(right_num)) => {
-                Ok(Object::Bool(left_num < right_num))
+            (
+                Object::Integer(start),
+                TokenType::DotDot | TokenType::DotDotEqual,
+                Object::Integer(end),
+            ) => Ok(Object::Range(Range {
+                start: *start,
+                end: *end,
+                inclusive: operator.r#type == TokenType::DotDotEqual,
+            })),
+            (
+                Object::Integer(_) | Object::Number(_) | Object::BigInt(_),
+                TokenType::DotDot | TokenType::DotDotEqual,
+                Object::Integer(_) | Object::Number(_) | Object::BigInt(_),
+            ) => Err(Error::runtime(
+                operator,
+                "E0300",
+                "Range bounds must be integers that fit in 64 bits.",
+            )),
+            (Object::String(left_str), TokenType::Plus, Object::String(right_str)) => {
+                Ok(Object::String(left_str.to_owned() + right_str))
             }
-            (Object::Number(left_num), TokenType::LessEqual, Object::Number(right_num)) => {
-                Ok(Object::Bool(left_num <= right_num))
+            (Object::BigInt(_), op, _) | (_, op, Object::BigInt(_)) if is_arithmetic(op) => {
+                self.bigint_binary(&left, operator, &right)
             }
             (_, TokenType::BangEqual, _) => Ok(Object::Bool(left != right)),
             (_, TokenType::EqualEqual, _) => Ok(Object::Bool(left == right)),
-            _ => self.runtime_error(&left, operator, &right),
+            // Integers that meet a float, or don't divide evenly, are promoted to floats.
+            _ => match (left.as_float(), &operator.r#type, right.as_float()) {
+                (Some(left_num), TokenType::Minus, Some(right_num)) => {
+                    Ok(Object::Number(left_num - right_num))
+                }
+                (Some(left_num), TokenType::Slash, Some(right_num)) => {
+                    Ok(Object::Number(left_num / right_num))
+                }
+                (Some(left_num), TokenType::Star, Some(right_num)) => {
+                    Ok(Object::Number(left_num * right_num))
+                }
+                (Some(left_num), TokenType::Plus, Some(right_num)) => {
+                    Ok(Object::Number(left_num + right_num))
+                }
+                (Some(left_num), TokenType::Greater, Some(right_num)) => {
+                    Ok(Object::Bool(left_num > right_num))
+                }
+                (Some(left_num), TokenType::GreaterEqual, Some(right_num)) => {
+                    Ok(Object::Bool(left_num >= right_num))
+                }
+                (Some(left_num), TokenType::Less, Some(right_num)) => {
+                    Ok(Object::Bool(left_num < right_num))
+                }
+                (Some(left_num), TokenType::LessEqual, Some(right_num)) => {
+                    Ok(Object::Bool(left_num <= right_num))
+                }
+                _ => self.runtime_error(&left, operator, &right),
+            },
         }
     }
 
@@ -199,35 +459,74 @@ impl expr::Visitor<Object> for Interpreter {
         self.evaluate(expr)
     }
 
+    fn visit_index_expr(
+        &mut self,
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+    ) -> Result<Object, Error> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
+        sequence::index(&object, &index)
+            .map_err(|message| Error::runtime(bracket, "E0306", message))
+    }
+
     fn visit_literal_expr(&self, value: &Object) -> Result<Object, Error> {
         Ok(value.clone())
     }
 
+    fn visit_tuple_expr(&mut self, elements: &[Expr]) -> Result<Object, Error> {
+        let mut values = vec![];
+        for element in elements {
+            values.push(self.evaluate(element)?);
+        }
+        Ok(Object::Tuple(Rc::new(values)))
+    }
+
     fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> Result<Object, Error> {
         let right = self.evaluate(right)?;
 
         match (operator.r#type.clone(), right.clone()) {
             (TokenType::Minus, Object::Number(num)) => Ok(Object::Number(-num)),
+            (TokenType::Minus, Object::Integer(int)) => {
+                self.integer_result(int.checked_neg(), operator)
+            }
+            (TokenType::Minus, Object::BigInt(big)) => Ok(Object::BigInt(-&big)),
             (TokenType::Bang, obj) => Ok(Object::Bool(!obj.is_truthy())),
-            _ => Err(Error::Runtime {
-                token: operator.clone(),
-                message: format!("invalid unary expr: {:?}{:?}", operator, right),
-            }),
+            _ => Err(Error::runtime(
+                operator,
+                "E0300",
+                "Operand must be a number.",
+            )),
         }
     }
 
-    fn visit_variable_expr(&mut self, name: &Token) -> Result<Object, Error> {
-        self.look_up_variable(name)
+    fn visit_variable_expr(&mut self, id: ExprId, name: &Token) -> Result<Object, Error> {
+        self.look_up_variable(id, name)
     }
 
-    fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> Result<Object, Error> {
+    fn visit_assign_expr(
+        &mut self,
+        id: ExprId,
+        name: &Token,
+        value: &Expr,
+    ) -> Result<Object, Error> {
         let value = self.evaluate(value)?;
-        if let Some(distance) = self.locals.get(name) {
-            self.environment
-                .borrow_mut()
-                .assign_at(*distance, name, value.clone())?;
-        } else {
-            self.environment.borrow_mut().assign(name, value.clone())?;
+        self.assign_variable(id, name, value.clone())?;
+        Ok(value)
+    }
+
+    fn visit_assign_tuple_expr(
+        &mut self,
+        ids: &[ExprId],
+        names: &[Token],
+        equals: &Token,
+        value: &Expr,
+    ) -> Result<Object, Error> {
+        let value = self.evaluate(value)?;
+        let elements = destructure(&value, names.len(), equals)?;
+        for ((id, name), element) in ids.iter().zip(names).zip(elements.iter()) {
+            self.assign_variable(*id, name, element.clone())?;
         }
         Ok(value)
     }
@@ -243,6 +542,10 @@ impl expr::Visitor<Object> for Interpreter {
             if left.is_truthy() {
                 return Ok(left);
             }
+        } else if operator.r#type == TokenType::QuestionQuestion {
+            if left != Object::Nil {
+                return Ok(left);
+            }
         } else if !left.is_truthy() {
             return Ok(left);
         }
@@ -254,10 +557,19 @@ impl expr::Visitor<Object> for Interpreter {
         callee: &Expr,
         paren: &Token,
         arguments: &[Expr],
+        piped: bool,
     ) -> Result<Object, Error> {
-        let callee = self.evaluate(callee)?;
-
         let mut args = vec![];
+        let mut arguments = arguments.iter();
+
+        // A pipeline's value comes first in the source, so it is evaluated first.
+        if piped {
+            if let Some(value) = arguments.next() {
+                args.push(self.evaluate(value)?);
+            }
+        }
+
+        let callee = self.evaluate(callee)?;
 
         for argument in arguments {
             args.push(self.evaluate(argument)?);
@@ -267,16 +579,17 @@ impl expr::Visitor<Object> for Interpreter {
             Object::Callable(function) => {
                 let arg_count = args.len();
                 if arg_count != function.arity() {
-                    Err(Error::Runtime {
-                        token: paren.clone(),
-                        message: format!(
+                    Err(Error::runtime(
+                        paren,
+                        "E0303",
+                        format!(
                             "Expected {} arguments but got {}.",
                             function.arity(),
                             arg_count
                         ),
-                    })
+                    ))
                 } else {
-                    function.call(self, &args)
+                    self.call_function(&function, paren, &args)
                 }
             }
             Object::Class(ref class) => {
@@ -284,24 +597,32 @@ impl expr::Visitor<Object> for Interpreter {
                 let instance = Instance::new_object(class);
                 if let Some(initializer) = class.borrow().find_method("init") {
                     if args_size != initializer.arity() {
-                        return Err(Error::Runtime {
-                            token: paren.clone(),
-                            message: format!(
+                        return Err(Error::runtime(
+                            paren,
+                            "E0303",
+                            format!(
                                 "Expected {} arguments but got {}.",
                                 initializer.arity(),
                                 args_size
                             ),
-                        });
+                        ));
                     }
-                    initializer.bind(instance.clone()).call(self, &args)?;
+                    self.call_function(&initializer.bind(instance.clone()), paren, &args)?;
+                } else if args_size != 0 {
+                    return Err(Error::runtime(
+                        paren,
+                        "E0303",
+                        format!("Expected 0 arguments but got {}.", args_size),
+                    ));
                 }
 
                 Ok(instance)
             }
-            _ => Err(Error::Runtime {
-                token: paren.clone(),
-                message: "Can only call functions and classes.".to_string(),
-            }),
+            _ => Err(Error::runtime(
+                paren,
+                "E0304",
+                "Can only call functions and classes.",
+            )),
         }
     }
 
@@ -310,10 +631,31 @@ impl expr::Visitor<Object> for Interpreter {
         if let Object::Instance(ref instance) = object {
             instance.borrow().get(name, &object)
         } else {
-            Err(Error::Runtime {
-                token: name.clone(),
-                message: "Only instances have properties.".to_string(),
-            })
+            Err(Error::runtime(
+                name,
+                "E0300",
+                "Only instances have properties.",
+            ))
+        }
+    }
+
+    fn visit_optional_get_expr(&mut self, object: &Expr, name: &Token) -> Result<Object, Error> {
+        let object = self.evaluate(object)?;
+        match object {
+            Object::Nil => Err(Error::ShortCircuit),
+            Object::Instance(ref instance) => instance.borrow().get(name, &object),
+            _ => Err(Error::runtime(
+                name,
+                "E0300",
+                "Only instances have properties.",
+            )),
+        }
+    }
+
+    fn visit_optional_chain_expr(&mut self, expr: &Expr) -> Result<Object, Error> {
+        match self.evaluate(expr) {
+            Err(Error::ShortCircuit) => Ok(Object::Nil),
+            result => result,
         }
     }
 
@@ -327,46 +669,61 @@ impl expr::Visitor<Object> for Interpreter {
 
         if let Object::Instance(ref instance) = object {
             let value = self.evaluate(value)?;
-            instance.borrow_mut().set(name, value);
-            let r = Object::Instance(Rc::clone(instance));
-            Ok(r)
+            instance.borrow_mut().set(name, value.clone());
+            Ok(value)
         } else {
-            Err(Error::Runtime {
-                token: name.clone(),
-                message: "Only instances have fields.".to_string(),
-            })
+            Err(Error::runtime(name, "E0300", "Only instances have fields."))
         }
     }
 
-    fn visit_this_expr(&mut self, keyword: &Token) -> Result<Object, Error> {
-        self.look_up_variable(keyword)
+    fn visit_this_expr(&mut self, id: ExprId, keyword: &Token) -> Result<Object, Error> {
+        self.look_up_variable(id, keyword)
     }
 
-    fn visit_super_expr(&mut self, keyword: &Token, method: &Token) -> Result<Object, Error> {
-        let distance = self
-            .locals
-            .get(keyword)
-            .expect("No local distance for 'super'.");
-        let superclass = self.environment.borrow().get_at(*distance, "super")?;
-
-        let instance = self.environment.borrow().get_at(*distance - 1, "this")?;
+    fn visit_super_expr(
+        &mut self,
+        id: ExprId,
+        keyword: &Token,
+        method: &Token,
+    ) -> Result<Object, Error> {
+        let slot = self.local(id);
+        let environment = self.environment.borrow();
+        let superclass = slot.and_then(|slot| environment.get_at(slot.depth, slot.index));
+        // `this` is the only local in the scope just inside the one holding `super`.
+        let instance = slot
+            .and_then(|slot| slot.depth.checked_sub(1))
+            .and_then(|depth| environment.get_at(depth, 0));
+        // Only reachable when the resolver never saw the expression, as in the REPL.
+        let (Some(Object::Class(superclass)), Some(instance)) = (superclass, instance) else {
+            return Err(Error::runtime(
+                keyword,
+                "E0300",
+                "Can't use 'super' outside of a class.",
+            ));
+        };
 
-        if let Object::Class(ref superclass) = superclass {
-            if let Some(method) = superclass.borrow().find_method(&method.lexeme) {
-                Ok(Object::Callable(method.bind(instance)))
-            } else {
-                Err(Error::Runtime {
-                    token: method.clone(),
-                    message: format!("Undefined property '{}'.", method.lexeme),
-                })
-            }
+        let superclass = superclass.borrow();
+        if let Some(method) = superclass.find_method(&method.lexeme) {
+            Ok(Object::Callable(method.bind(instance)))
         } else {
-            unreachable!()
+            Err(Error::runtime(
+                method,
+                "E0302",
+                format!("Undefined property '{}'.", method.lexeme),
+            )
+            .with_hint(did_you_mean(
+                &method.lexeme,
+                superclass.all_method_names().iter().map(String::as_str),
+            )))
         }
     }
 }
 
 impl stmt::Visitor<()> for Interpreter {
+    fn visit_null_stmt(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
     fn visit_block_stmt(&mut self, statements: &[Stmt]) -> Result<(), Error> {
         self.execute_block(
             statements,
@@ -385,7 +742,9 @@ impl stmt::Visitor<()> for Interpreter {
     fn visit_print_stmt(&mut self, expression: &Expr) -> Result<(), Error> {
         match self.evaluate(expression) {
             Ok(value) => {
-                println!("{}", value);
+                if !self.quiet {
+                    println!("{}", value);
+                }
                 Ok(())
             }
             Err(e) => Err(e),
@@ -402,23 +761,45 @@ impl stmt::Visitor<()> for Interpreter {
         Ok(())
     }
 
+    fn visit_var_tuple_stmt(
+        &mut self,
+        keyword: &Token,
+        names: &[Token],
+        initializer: &Expr,
+    ) -> Result<(), Error> {
+        let value = self.evaluate(initializer)?;
+        let elements = destructure(&value, names.len(), keyword)?;
+        for (name, element) in names.iter().zip(elements.iter()) {
+            self.environment
+                .borrow_mut()
+                .define(&name.lexeme, element.clone());
+        }
+        Ok(())
+    }
+
     fn visit_if_stmt(
         &mut self,
         condition: &Expr,
         then_branch: &Stmt,
         else_branch: &Option<Stmt>,
     ) -> Result<(), Error> {
-        if self.evaluate(condition).is_ok_and(|obj| obj.is_truthy()) {
+        if self.evaluate(condition)?.is_truthy() {
             self.execute(then_branch)
-        } else if else_branch.is_some() {
-            self.execute(else_branch.as_ref().unwrap())
+        } else if let Some(else_branch) = else_branch {
+            self.execute(else_branch)
         } else {
             Ok(())
         }
     }
 
-    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> Result<(), Error> {
-        while self.evaluate(condition).is_ok_and(|obj| obj.is_truthy()) {
+    fn visit_while_stmt(
+        &mut self,
+        keyword: &Token,
+        condition: &Expr,
+        body: &Stmt,
+    ) -> Result<(), Error> {
+        while self.evaluate(condition)?.is_truthy() {
+            self.step(keyword)?;
             self.execute(body)?
         }
         Ok(())
@@ -466,21 +847,20 @@ impl stmt::Visitor<()> for Interpreter {
             .map(|expr| {
                 if let Object::Class(ref lox_class) = self.evaluate(expr)? {
                     Ok(Rc::clone(lox_class))
-                } else if let Expr::Variable { name } = expr {
-                    Err(Error::Runtime {
-                        token: name.clone(),
-                        message: "Superclass must be a class.".to_string(),
-                    })
                 } else {
-                    unreachable!()
+                    let token = match expr {
+                        Expr::Variable { name, .. } => name,
+                        _ => name,
+                    };
+                    Err(Error::runtime(
+                        token,
+                        "E0300",
+                        "Superclass must be a class.",
+                    ))
                 }
             })
             .transpose()?;
 
-        self.environment
-            .borrow_mut()
-            .define(&name.lexeme, Object::Nil);
-
         if let Some(ref class) = superclass {
             self.environment = Rc::new(RefCell::new(Environment::from(&self.environment)));
             self.environment
@@ -490,7 +870,10 @@ impl stmt::Visitor<()> for Interpreter {
 
         let mut class_methods: HashMap<String, Function> = HashMap::new();
         for method in methods {
-            if let Stmt::Function { name, params, body } = method {
+            if let Stmt::Function {
+                name, params, body, ..
+            } = method
+            {
                 let function = Function::User {
                     name: Box::new(name.clone()),
                     params: params.clone(),
@@ -512,25 +895,27 @@ impl stmt::Visitor<()> for Interpreter {
         let class = Object::Class(Rc::new(RefCell::new(lox_class)));
 
         if superclass.is_some() {
-            let parent = self
-                .environment
-                .borrow()
-                .enclosing
-                .clone()
-                .expect("Superclass environment has no parent.");
-            self.environment = parent;
+            let parent = self.environment.borrow().enclosing.clone();
+            if let Some(parent) = parent {
+                self.environment = parent;
+            }
         }
 
-        self.environment.borrow_mut().assign(name, class)?;
+        // Defined only now, once the class exists, which still gives it the slot the resolver
+        // expects since nothing else is declared in this scope in between.
+        self.environment.borrow_mut().define(&name.lexeme, class);
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::expr::ExprId;
     use crate::interpreter::Interpreter;
     use crate::parser::Parser;
+    use crate::resolver::Resolver;
     use crate::scanner::Scanner;
+    use crate::token::Object;
 
     use insta::assert_debug_snapshot;
 
@@ -593,4 +978,34 @@ mod tests {
 
     test_repl!(var_assign, &["var x = (40 - 30) * 20;", "print x;"]);
     test_repl!(repl_err, &["(40", "var x = 10;", "print x;"]);
+
+    #[test]
+    fn later_parses_do_not_reuse_resolved_ids() {
+        let mut interpreter = Interpreter::new();
+        let mut next_id = ExprId::default();
+        // The first program resolves its `x` as a local one scope out.
+        for line in [
+            "fun f() { var x = 1; { return x; } }",
+            "var x = \"global\";",
+        ] {
+            let tokens = Scanner::new(line.to_string()).scan_tokens();
+            let mut parser = Parser::new(tokens).with_first_id(next_id);
+            let statements = parser.parse().unwrap();
+            next_id = parser.next_id();
+            Resolver::new(&mut interpreter)
+                .resolve_stmts(&statements)
+                .unwrap();
+            interpreter.interpret(&statements).unwrap();
+        }
+
+        let tokens = Scanner::new("x".to_string()).scan_tokens();
+        let expressions = Parser::new(tokens)
+            .with_first_id(next_id)
+            .parse_exprs()
+            .unwrap();
+        assert_eq!(
+            interpreter.evaluate(&expressions[0]).unwrap(),
+            Object::String("global".to_string())
+        );
+    }
 }
@@ -1,11 +1,19 @@
+pub mod bigint;
 pub mod class;
+pub mod diagnostic;
 pub mod environment;
 pub mod error;
+pub mod expect;
 pub mod expr;
 pub mod function;
 pub mod interpreter;
+pub mod native;
 pub mod parser;
 pub mod resolver;
 pub mod scanner;
+pub mod sequence;
 pub mod stmt;
+pub mod suggest;
+pub mod symbol;
+pub mod testing;
 pub mod token;
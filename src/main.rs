@@ -1,32 +1,71 @@
 use std::fs;
 use std::io::{self, Write};
-use std::process::exit;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command};
+use std::thread;
 
+use treelox::diagnostic::{emit, Diagnostic, Stage};
 use treelox::error::Error;
+use treelox::expect::{Expectations, Outcome};
+use treelox::expr::ExprId;
 use treelox::interpreter::Interpreter;
 use treelox::parser::Parser;
-use treelox::resolver::Resolver;
+use treelox::resolver::{Resolver, Warning};
 use treelox::scanner::Scanner;
+use treelox::stmt::Stmt;
+use treelox::testing;
+use treelox::token::Span;
 
 struct Lox {
     interpreter: Interpreter,
+    options: Options,
+    /// Where the next parse starts numbering expressions, so each REPL line gets IDs of its own.
+    next_id: ExprId,
 }
 
-enum Input {
+#[derive(Default)]
+struct Options {
+    /// Treat warnings like errors, refusing to run the program.
+    deny_warnings: bool,
+    allowed: Vec<Warning>,
+    error_format: ErrorFormat,
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum ErrorFormat {
+    #[default]
+    Human,
+    /// One JSON object per line, for editors and CI.
+    Json,
+}
+
+#[derive(Clone, Copy)]
+enum Input<'a> {
     Repl,
-    File,
+    File(&'a str),
+}
+
+impl Input<'_> {
+    fn name(&self) -> &str {
+        match self {
+            Input::Repl => "<repl>",
+            Input::File(path) => path,
+        }
+    }
 }
 
 impl Lox {
-    fn new() -> Self {
+    fn new(options: Options) -> Self {
         Lox {
             interpreter: Interpreter::new(),
+            options,
+            next_id: ExprId::default(),
         }
     }
 
     fn run_file(&mut self, path: &str) -> Result<(), Error> {
         let source = fs::read_to_string(path)?;
-        self.run(source, Input::File)
+        self.run(source, Input::File(path))
     }
 
     fn run_prompt(&mut self) -> Result<(), Error> {
@@ -43,56 +82,322 @@ impl Lox {
     }
 
     fn run(&mut self, source: String, input: Input) -> Result<(), Error> {
-        let mut scanner = Scanner::new(source);
-        let tokens = scanner.scan_tokens();
-
-        let mut parser = Parser::new(tokens);
-        let mut resolver = Resolver::new(&mut self.interpreter);
-        match input {
-            Input::Repl => match parser.parse_exprs() {
-                Ok(expressions) => {
-                    self.interpreter.interpret_expressions(&expressions)?;
+        let result = self.execute(&source, input);
+        match &result {
+            Err(Error::Compile { diagnostics }) => {
+                for diagnostic in diagnostics {
+                    self.report(diagnostic, &source, input);
                 }
-                Err(_) => {
-                    let statements = parser.parse()?;
-                    resolver.resolve_stmts(&statements)?;
-                    if resolver.had_error {
-                        return Ok(());
-                    }
-                    self.interpreter.interpret(&statements)?;
+            }
+            Err(error) => {
+                if let Some(diagnostic) = Diagnostic::from_error(error) {
+                    let traceback = self.interpreter.take_traceback();
+                    self.report(&diagnostic.with_traceback(traceback), &source, input);
                 }
-            },
-            Input::File => {
-                let statements = parser.parse()?;
-                resolver.resolve_stmts(&statements)?;
-                if resolver.had_error {
-                    return Ok(());
+            }
+            Ok(()) => {}
+        }
+        result
+    }
+
+    fn report(&self, diagnostic: &Diagnostic, source: &str, input: Input) {
+        match self.options.error_format {
+            ErrorFormat::Human => emit(diagnostic, source),
+            ErrorFormat::Json => eprintln!("{}", diagnostic.to_json(input.name())),
+        }
+    }
+
+    fn execute(&mut self, source: &str, input: Input) -> Result<(), Error> {
+        let mut scanner = Scanner::new(source.to_string());
+        let tokens = scanner.scan_tokens();
+        let mut parser = Parser::new(tokens).with_first_id(self.next_id);
+
+        if let (Input::Repl, true) = (&input, scanner.diagnostics.is_empty()) {
+            if let Ok(expressions) = parser.parse_exprs() {
+                self.next_id = parser.next_id();
+                return self.interpreter.interpret_expressions(&expressions);
+            }
+        }
+
+        let mut diagnostics = scanner.diagnostics;
+        let parsed = parser.parse();
+        self.next_id = parser.next_id();
+        let statements = match parsed {
+            Ok(statements) => statements,
+            Err(errors) => {
+                diagnostics.extend(errors.iter().filter_map(Diagnostic::from_error));
+                vec![]
+            }
+        };
+
+        if diagnostics.is_empty() {
+            let mut resolver = Resolver::new(&mut self.interpreter);
+            for warning in &self.options.allowed {
+                resolver.allow(*warning);
+            }
+            resolver.resolve_stmts(&statements)?;
+            diagnostics = resolver.diagnostics;
+        }
+
+        diagnostics.sort_by_key(|diagnostic| {
+            (
+                diagnostic.line,
+                diagnostic.span.map_or(0, |span| span.column),
+            )
+        });
+        let denied = self.options.deny_warnings && !diagnostics.is_empty();
+        if denied || diagnostics.iter().any(Diagnostic::is_error) {
+            return Err(Error::Compile { diagnostics });
+        }
+        for warning in &diagnostics {
+            self.report(warning, source, input);
+        }
+
+        self.interpreter.interpret(&statements)
+    }
+}
+
+impl ErrorFormat {
+    /// The format the last `--error-format` flag asks for, known before the rest of the command
+    /// line is checked so that problems with it can be reported in that format too.
+    fn from_args(args: &[String]) -> Self {
+        args.iter()
+            .rev()
+            .find_map(|arg| arg.strip_prefix("--error-format="))
+            .map_or(ErrorFormat::Human, |format| match format {
+                "json" => ErrorFormat::Json,
+                _ => ErrorFormat::Human,
+            })
+    }
+
+    /// Reports a problem with the command line or a file it names, which has no place in any
+    /// script, and exits with `status`.
+    fn fail(self, code: &'static str, file: &str, message: &str, status: i32) -> ! {
+        match self {
+            ErrorFormat::Human => eprintln!("{}", message),
+            ErrorFormat::Json => {
+                let diagnostic = Diagnostic::new(Stage::Cli, code, message, 0, Span::default());
+                eprintln!("{}", diagnostic.to_json(file));
+            }
+        }
+        exit(status)
+    }
+
+    fn usage(self) -> ! {
+        self.fail(
+            "E0400",
+            "",
+            concat!(
+                "Usage: treelox [--deny-warnings] [--allow=<warning>]... [--error-format=human|json] [script]\n",
+                "       treelox test [--error-format=human|json] <file or directory>..."
+            ),
+            64,
+        )
+    }
+
+    fn could_not_read(self, path: &str, error: impl std::fmt::Display) -> ! {
+        let message = format!("Could not read '{}': {}.", path, error);
+        self.fail("E0401", path, &message, 66)
+    }
+}
+
+/// Runs every script under `paths`, exiting with 1 if any fails. Each script is checked against
+/// its `// expect` annotations, and scripts that define `test_*` functions then have each one
+/// run as a unit test.
+fn test(paths: &[String], format: ErrorFormat) -> ! {
+    let mut scripts = vec![];
+    for path in paths {
+        if let Err(error) = collect_scripts(Path::new(path), &mut scripts) {
+            format.could_not_read(path, error)
+        }
+    }
+
+    let interpreter = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("treelox"));
+    let (mut passed, mut failed) = (0, 0);
+    for script in &scripts {
+        let problems = match check_script(&interpreter, script) {
+            Ok(problems) => problems,
+            Err(error) => vec![format!("could not run: {}", error)],
+        };
+        if problems.is_empty() {
+            passed += 1;
+        } else {
+            failed += 1;
+            println!("FAIL {}", script.display());
+            for problem in problems {
+                println!("  {}", problem.replace('\n', "\n  "));
+            }
+        }
+
+        let source = fs::read_to_string(script).unwrap_or_default();
+        let Some(statements) = unit_tests(&source) else {
+            continue;
+        };
+        for result in testing::run(&statements) {
+            let status = if result.passed() { "ok  " } else { "FAIL" };
+            println!(
+                "{} {}::{} ({:.2?})",
+                status,
+                script.display(),
+                result.name,
+                result.duration
+            );
+            match result.failure {
+                Some(failure) => {
+                    failed += 1;
+                    let report = failure.render(&source, false);
+                    println!("  {}", report.trim_end().replace('\n', "\n  "));
                 }
-                self.interpreter.interpret(&statements)?;
+                None => passed += 1,
+            }
+        }
+    }
+
+    println!("{} passed, {} failed.", passed, failed);
+    exit(if failed == 0 { 0 } else { 1 })
+}
+
+/// The statements of a script that parses and defines at least one `test_*` function.
+fn unit_tests(source: &str) -> Option<Vec<Stmt>> {
+    let mut scanner = Scanner::new(source.to_string());
+    let tokens = scanner.scan_tokens();
+    let statements = Parser::new(tokens).parse().ok()?;
+    let has_tests = scanner.diagnostics.is_empty() && !testing::discover(&statements).is_empty();
+    has_tests.then_some(statements)
+}
+
+/// Parts of the official test suite written for other interpreters, which directory runs skip:
+/// the scanner and expression printers of the early chapters and clox's bytecode limits, as its
+/// own jlox runner does, and the literals test that expects `-0` to print as a negative zero,
+/// since `0` is an integer here. `integer/literals.lox` checks the rest of that test. Naming one
+/// of them directly still runs it.
+const OTHER_INTERPRETERS: &[&str] = &[
+    "scanning",
+    "expressions",
+    "limit/loop_too_large.lox",
+    "limit/no_reuse_constants.lox",
+    "limit/too_many_constants.lox",
+    "limit/too_many_locals.lox",
+    "limit/too_many_upvalues.lox",
+    "number/literals.lox",
+];
+
+/// Adds `path` if it is a script, or every script beneath it if it is a directory, in order.
+fn collect_scripts(path: &Path, scripts: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        fs::metadata(path)?;
+        scripts.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        if OTHER_INTERPRETERS
+            .iter()
+            .any(|skipped| entry.ends_with(skipped))
+        {
+            continue;
+        }
+        if entry.is_dir()
+            || entry
+                .extension()
+                .is_some_and(|extension| extension == "lox")
+        {
+            collect_scripts(&entry, scripts)?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs one script in a separate interpreter process so its output and exit code can be checked.
+fn check_script(interpreter: &Path, script: &Path) -> io::Result<Vec<String>> {
+    let expectations = Expectations::parse(&fs::read_to_string(script)?);
+    let output = Command::new(interpreter).arg(script).output()?;
+    Ok(expectations.check(&Outcome {
+        status: output.status.code(),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    }))
+}
+
+/// Deep recursion in Lox uses a lot of native stack, so the interpreter runs on a thread with
+/// room for its full call depth rather than on the main thread.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+fn main() {
+    match thread::Builder::new().stack_size(STACK_SIZE).spawn(cli) {
+        Ok(interpreter) => {
+            if interpreter.join().is_err() {
+                exit(101)
             }
         }
-        Ok(())
+        Err(_) => cli(),
     }
 }
 
-fn main() -> Result<(), Error> {
-    let args: Vec<String> = std::env::args().collect();
-    let mut lox = Lox::new();
-    match &args[..] {
-        [_, file] => match lox.run_file(file) {
+fn cli() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let format = ErrorFormat::from_args(&args);
+    if let Some(("test", args)) = args
+        .split_first()
+        .map(|(first, rest)| (first.as_str(), rest))
+    {
+        let mut paths = vec![];
+        for arg in args {
+            match arg.strip_prefix("--error-format=") {
+                Some("human" | "json") => {}
+                Some(_) => format.usage(),
+                None if arg.starts_with("--") => format.usage(),
+                None => paths.push(arg.clone()),
+            }
+        }
+        if paths.is_empty() {
+            format.usage()
+        }
+        test(&paths, format)
+    }
+
+    let mut options = Options {
+        error_format: format,
+        ..Options::default()
+    };
+    let mut scripts = vec![];
+    for arg in args {
+        if arg == "--deny-warnings" {
+            options.deny_warnings = true;
+        } else if let Some(name) = arg.strip_prefix("--allow=") {
+            let Some(warning) = Warning::from_name(name) else {
+                format.fail("E0400", "", &format!("Unknown warning '{}'.", name), 64)
+            };
+            options.allowed.push(warning);
+        } else if let Some(name) = arg.strip_prefix("--error-format=") {
+            if !matches!(name, "human" | "json") {
+                format.usage()
+            }
+        } else if arg.starts_with("--") {
+            format.usage()
+        } else {
+            scripts.push(arg);
+        }
+    }
+
+    let mut lox = Lox::new(options);
+    match &scripts[..] {
+        [file] => match lox.run_file(file) {
             Ok(_) => (),
-            Err(Error::Runtime { message, .. }) => {
-                eprintln!("Error: {}", message);
+            Err(Error::Runtime { .. }) | Err(Error::Return { .. }) | Err(Error::ShortCircuit) => {
                 exit(70)
             }
-            Err(Error::Parse { .. }) => exit(65),
-            Err(Error::Io(_)) | Err(Error::Return { .. }) => unimplemented!(),
+            Err(Error::Parse { .. }) | Err(Error::Compile { .. }) => exit(65),
+            Err(Error::Io(error)) => format.could_not_read(file, error),
         },
-        [_] => lox.run_prompt()?,
-        _ => {
-            eprintln!("Usage: treelox [script]");
-            exit(64)
+        [] => {
+            if let Err(error) = lox.run_prompt() {
+                format.fail("E0402", Input::Repl.name(), &error.to_string(), 74)
+            }
         }
+        _ => format.usage(),
     }
-    Ok(())
 }
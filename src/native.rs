@@ -0,0 +1,200 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    bigint::BigInt,
+    environment::Environment,
+    function::{Function, NativeError, NativeFn},
+    sequence,
+    token::Object,
+};
+
+pub(crate) fn define_natives(globals: &mut Environment) {
+    define(globals, "clock", 0, clock);
+    define(globals, "len", 1, len);
+    define(globals, "toBigInt", 1, to_bigint);
+    define(globals, "toNumber", 1, to_number);
+    define(globals, "toString", 1, to_string);
+    define(globals, "fields", 1, fields);
+    define(globals, "methods", 1, methods);
+    define(globals, "hasField", 2, has_field);
+    define(globals, "getField", 2, get_field);
+    define(globals, "setField", 3, set_field);
+    define(globals, "superclassOf", 1, superclass_of);
+    define(globals, "assert", 2, assert);
+    define(globals, "assertEqual", 2, assert_equal);
+}
+
+fn define(globals: &mut Environment, name: &str, arity: usize, body: NativeFn) {
+    globals.define(
+        name,
+        Object::Callable(Function::Native {
+            arity,
+            body: Box::new(body),
+        }),
+    );
+}
+
+fn clock(_: &[Object]) -> Result<Object, NativeError> {
+    Ok(Object::Number(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| "Could not retrieve time.".to_string())?
+            .as_millis() as f64,
+    ))
+}
+
+fn len(arguments: &[Object]) -> Result<Object, NativeError> {
+    sequence::length(&arguments[0])
+        .map(|len| Object::Integer(len as i64))
+        .ok_or_else(|| {
+            "len() expects a string, list, tuple or range."
+                .to_string()
+                .into()
+        })
+}
+
+fn to_bigint(arguments: &[Object]) -> Result<Object, NativeError> {
+    let value = match &arguments[0] {
+        Object::BigInt(big) => Some(big.clone()),
+        Object::Integer(int) => Some(BigInt::from(*int)),
+        Object::Number(num) => BigInt::from_f64(*num),
+        Object::String(string) => BigInt::from_str_radix(string.trim(), 10),
+        _ => None,
+    };
+    value
+        .map(Object::BigInt)
+        .ok_or_else(|| format!("Cannot convert {} to a BigInt.", arguments[0]).into())
+}
+
+/// Converts to an integer when the value fits in 64 bits, and to the nearest float otherwise.
+fn to_number(arguments: &[Object]) -> Result<Object, NativeError> {
+    match &arguments[0] {
+        Object::Integer(_) | Object::Number(_) => Ok(arguments[0].clone()),
+        Object::BigInt(big) => Ok(big
+            .to_i64()
+            .map_or_else(|| Object::Number(big.to_f64()), Object::Integer)),
+        Object::String(string) => {
+            let string = string.trim();
+            string
+                .parse::<i64>()
+                .map(Object::Integer)
+                .or_else(|_| string.parse::<f64>().map(Object::Number))
+                .map_err(|_| format!("Cannot convert \"{}\" to a number.", string).into())
+        }
+        _ => Err(format!("Cannot convert {} to a number.", arguments[0]).into()),
+    }
+}
+
+fn to_string(arguments: &[Object]) -> Result<Object, NativeError> {
+    Ok(Object::String(arguments[0].to_string()))
+}
+
+fn names(names: Vec<String>) -> Object {
+    Object::List(Rc::new(RefCell::new(
+        names.into_iter().map(Object::String).collect(),
+    )))
+}
+
+fn property_name<'a>(native: &str, name: &'a Object) -> Result<&'a str, String> {
+    match name {
+        Object::String(name) => Ok(name),
+        _ => Err(format!("{}() expects a property name string.", native)),
+    }
+}
+
+fn fields(arguments: &[Object]) -> Result<Object, NativeError> {
+    match &arguments[0] {
+        Object::Instance(instance) => Ok(names(instance.borrow().field_names())),
+        _ => Err("fields() expects an instance.".to_string().into()),
+    }
+}
+
+fn methods(arguments: &[Object]) -> Result<Object, NativeError> {
+    match &arguments[0] {
+        Object::Class(class) => Ok(names(class.borrow().method_names())),
+        _ => Err("methods() expects a class.".to_string().into()),
+    }
+}
+
+fn has_field(arguments: &[Object]) -> Result<Object, NativeError> {
+    let name = property_name("hasField", &arguments[1])?;
+    match &arguments[0] {
+        Object::Instance(instance) => Ok(Object::Bool(instance.borrow().has_field(name))),
+        _ => Err("hasField() expects an instance.".to_string().into()),
+    }
+}
+
+fn get_field(arguments: &[Object]) -> Result<Object, NativeError> {
+    let name = property_name("getField", &arguments[1])?;
+    match &arguments[0] {
+        Object::Instance(instance) => instance
+            .borrow()
+            .get_field(name, &arguments[0])
+            .ok_or_else(|| NativeError::new("E0302", format!("Undefined property '{}'.", name))),
+        _ => Err("getField() expects an instance.".to_string().into()),
+    }
+}
+
+fn set_field(arguments: &[Object]) -> Result<Object, NativeError> {
+    let name = property_name("setField", &arguments[1])?;
+    match &arguments[0] {
+        Object::Instance(instance) => {
+            instance.borrow_mut().set_field(name, arguments[2].clone());
+            Ok(arguments[2].clone())
+        }
+        _ => Err("setField() expects an instance.".to_string().into()),
+    }
+}
+
+fn superclass_of(arguments: &[Object]) -> Result<Object, NativeError> {
+    match &arguments[0] {
+        Object::Class(class) => Ok(class
+            .borrow()
+            .superclass
+            .as_ref()
+            .map_or(Object::Nil, |superclass| {
+                Object::Class(Rc::clone(superclass))
+            })),
+        _ => Err("superclassOf() expects a class.".to_string().into()),
+    }
+}
+
+fn assert(arguments: &[Object]) -> Result<Object, NativeError> {
+    if arguments[0].is_truthy() {
+        Ok(Object::Nil)
+    } else {
+        Err(NativeError::new(
+            "E0307",
+            format!("Assertion failed: {}", arguments[1]),
+        ))
+    }
+}
+
+/// Compares like `==`, showing both values on failure with strings quoted, so `1` and `"1"`
+/// can be told apart.
+fn assert_equal(arguments: &[Object]) -> Result<Object, NativeError> {
+    let (actual, expected) = (&arguments[0], &arguments[1]);
+    if actual == expected {
+        Ok(Object::Nil)
+    } else {
+        Err(NativeError::new(
+            "E0307",
+            format!(
+                "Assertion failed: expected {} but got {}.",
+                quoted(expected),
+                quoted(actual)
+            ),
+        ))
+    }
+}
+
+fn quoted(value: &Object) -> String {
+    match value {
+        Object::String(string) => format!("{:?}", string),
+        _ => value.to_string(),
+    }
+}
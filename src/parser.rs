@@ -1,14 +1,26 @@
+use std::mem;
+
 use crate::{
-    error::{parser_error, Error},
-    expr::Expr,
+    error::Error,
+    expr::{Expr, ExprId},
     stmt::Stmt,
-    token::{Object, Token, TokenType},
+    token::{Object, Span, Token, TokenType},
 };
 
-#[derive(Default, Debug, Clone, PartialEq)]
+/// How deeply blocks, statements and expressions may nest before parsing gives up, so that
+/// pathological input fails with an error instead of overflowing the stack.
+const MAX_NESTING: usize = 256;
+
+#[derive(Default, Debug)]
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    errors: Vec<Error>,
+    depth: usize,
+    /// Set once the input nests too deeply, after which no more errors are reported.
+    too_deep: bool,
+    /// The ID the next variable, `this` or `super` expression gets.
+    next_id: ExprId,
 }
 
 impl Parser {
@@ -19,12 +31,37 @@ impl Parser {
         }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, Error> {
+    /// Starts numbering expressions from `first` rather than zero, so that code parsed in
+    /// several pieces, like lines typed into the REPL, never reuses an ID.
+    pub fn with_first_id(mut self, first: ExprId) -> Self {
+        self.next_id = first;
+        self
+    }
+
+    /// The ID the next expression would get, for continuing the numbering in another parser.
+    pub fn next_id(&self) -> ExprId {
+        self.next_id
+    }
+
+    fn id(&mut self) -> ExprId {
+        let id = self.next_id;
+        self.next_id = ExprId(id.0 + 1);
+        id
+    }
+
+    /// Parses the whole program, recovering after each syntax error so that every error in the
+    /// file is returned together.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<Error>> {
         let mut statements = vec![];
         while !self.is_at_end() {
-            statements.push(self.declaration()?);
+            statements.extend(self.declaration());
+        }
+
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(mem::take(&mut self.errors))
         }
-        Ok(statements)
     }
 
     pub fn parse_exprs(&mut self) -> Result<Vec<Expr>, Error> {
@@ -36,32 +73,49 @@ impl Parser {
     }
 
     fn expression(&mut self) -> Result<Expr, Error> {
-        self.assignment()
+        self.nested(Self::assignment)
+    }
+
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        if self.depth >= MAX_NESTING {
+            // Recovering at the next statement would only go as deep again, so the limit is
+            // reported once and the rest of the input is skipped.
+            let error = self.error(&self.peek(), "E0100", "Too much nesting.");
+            self.record(error);
+            self.too_deep = true;
+            self.current = self.tokens.len() - 1;
+            return Err(self.error(&self.peek(), "E0100", "Too much nesting."));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
     }
 
-    fn declaration(&mut self) -> Result<Stmt, Error> {
+    /// Parses a declaration, or records the error and skips to the next statement.
+    fn declaration(&mut self) -> Option<Stmt> {
         let statement = if self.r#match(&[TokenType::Fun]) {
             self.function("function")
         } else if self.r#match(&[TokenType::Var]) {
             self.var_declaration()
         } else if self.r#match(&[TokenType::Class]) {
             self.class_declaration()
-        } else if self.r#match(&[TokenType::If]) {
-            self.if_statement()
         } else {
             self.statement()
         };
 
         match statement {
-            Err(e) => {
+            Ok(statement) => Some(statement),
+            Err(error) => {
+                self.record(error);
                 self.synchronize();
-                Err(e)
+                None
             }
-            stmt => stmt,
         }
     }
 
     fn class_declaration(&mut self) -> Result<Stmt, Error> {
+        let start = self.previous().span;
         let name = self.consume(&TokenType::Identifier, "Expect class name.")?;
         let superclass = if self.r#match(&[TokenType::Less]) {
             self.consume(&TokenType::Identifier, "Expect superclass name.")?;
@@ -82,12 +136,18 @@ impl Parser {
 
         Ok(Stmt::Class {
             name,
-            superclass: superclass.map(|name| Expr::Variable { name }),
+            superclass: superclass.map(|name| Expr::Variable {
+                id: self.id(),
+                span: name.span,
+                name,
+            }),
             methods,
+            span: self.span_from(start),
         })
     }
 
     fn if_statement(&mut self) -> Result<Stmt, Error> {
+        let start = self.previous().span;
         self.consume(&TokenType::LeftParen, "Expect '(' after 'if'.")?;
         let condition = self.expression()?;
         self.consume(&TokenType::RightParen, "Expect ')' after if condition.")?;
@@ -104,11 +164,18 @@ impl Parser {
             condition,
             then_branch,
             else_branch,
+            span: self.span_from(start),
         })
     }
 
     fn statement(&mut self) -> Result<Stmt, Error> {
-        if self.r#match(&[TokenType::Print]) {
+        self.nested(Self::statement_inner)
+    }
+
+    fn statement_inner(&mut self) -> Result<Stmt, Error> {
+        if self.r#match(&[TokenType::If]) {
+            self.if_statement()
+        } else if self.r#match(&[TokenType::Print]) {
             self.print_statement()
         } else if self.r#match(&[TokenType::Return]) {
             self.return_statement()
@@ -117,8 +184,11 @@ impl Parser {
         } else if self.r#match(&[TokenType::While]) {
             self.while_statement()
         } else if self.r#match(&[TokenType::LeftBrace]) {
+            let start = self.previous().span;
+            let statements = self.block()?;
             Ok(Stmt::Block {
-                statements: self.block()?,
+                statements,
+                span: self.span_from(start),
             })
         } else {
             self.expression_statement()
@@ -134,10 +204,16 @@ impl Parser {
         };
 
         self.consume(&TokenType::Semicolon, "Expect ';' after return value.")?;
-        Ok(Stmt::Return { keyword, value })
+        Ok(Stmt::Return {
+            span: self.span_from(keyword.span),
+            keyword,
+            value,
+        })
     }
 
     fn for_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous();
+        let start = keyword.span;
         self.consume(&TokenType::LeftParen, "Expect '(' after 'for'.")?;
 
         let initializer = if self.r#match(&[TokenType::Semicolon]) {
@@ -148,7 +224,7 @@ impl Parser {
             Some(self.expression_statement()?)
         };
 
-        let mut condition = if !self.check(&TokenType::Semicolon) {
+        let condition = if !self.check(&TokenType::Semicolon) {
             Some(self.expression()?)
         } else {
             None
@@ -165,27 +241,36 @@ impl Parser {
         self.consume(&TokenType::RightParen, "Expect ')' after loop condition.")?;
 
         let mut body = self.statement()?;
+        // Every statement the loop desugars into covers the whole `for`.
+        let span = self.span_from(start);
 
         if let Some(incr) = increment {
             body = Stmt::Block {
-                statements: vec![body, Stmt::Expression { expr: incr }],
+                statements: vec![
+                    body,
+                    Stmt::Expression {
+                        span: incr.span(),
+                        expr: incr,
+                    },
+                ],
+                span,
             };
         }
 
-        if condition.is_none() {
-            condition = Some(Expr::Literal {
-                value: Object::Bool(true),
-            });
-        }
-
         body = Stmt::While {
-            condition: condition.unwrap(),
+            keyword,
+            condition: condition.unwrap_or(Expr::Literal {
+                value: Object::Bool(true),
+                span,
+            }),
             body: Box::new(body),
+            span,
         };
 
         if let Some(init) = initializer {
             body = Stmt::Block {
                 statements: vec![init, body],
+                span,
             }
         }
 
@@ -193,12 +278,21 @@ impl Parser {
     }
 
     fn print_statement(&mut self) -> Result<Stmt, Error> {
+        let start = self.previous().span;
         let expr = self.expression()?;
         self.consume(&TokenType::Semicolon, "Expect ';' after value.")?;
-        Ok(Stmt::Print { expr })
+        Ok(Stmt::Print {
+            expr,
+            span: self.span_from(start),
+        })
     }
 
     fn var_declaration(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous();
+        if self.r#match(&[TokenType::LeftParen]) {
+            return self.var_tuple_declaration(keyword);
+        }
+
         let name = self.consume(&TokenType::Identifier, "Expect variable name.")?;
 
         let initializer = if self.r#match(&[TokenType::Equal]) {
@@ -212,25 +306,74 @@ impl Parser {
             "Expect ';' after variable declaration.",
         )?;
 
-        Ok(Stmt::Var { name, initializer })
+        Ok(Stmt::Var {
+            name,
+            initializer,
+            span: self.span_from(keyword.span),
+        })
+    }
+
+    fn var_tuple_declaration(&mut self, keyword: Token) -> Result<Stmt, Error> {
+        let mut names = vec![];
+
+        loop {
+            names.push(self.consume(&TokenType::Identifier, "Expect variable name.")?);
+            if !self.r#match(&[TokenType::Comma]) {
+                break;
+            }
+        }
+
+        self.consume(
+            &TokenType::RightParen,
+            "Expect ')' after destructuring pattern.",
+        )?;
+        self.consume(&TokenType::Equal, "Expect '=' after destructuring pattern.")?;
+        let initializer = self.expression()?;
+        self.consume(
+            &TokenType::Semicolon,
+            "Expect ';' after variable declaration.",
+        )?;
+
+        Ok(Stmt::VarTuple {
+            span: self.span_from(keyword.span),
+            keyword,
+            names,
+            initializer,
+        })
     }
 
     fn while_statement(&mut self) -> Result<Stmt, Error> {
+        let keyword = self.previous();
         self.consume(&TokenType::LeftParen, "Expect '(' after 'while'.")?;
         let condition = self.expression()?;
         self.consume(&TokenType::RightParen, "Expect ')' after condition.")?;
         let body = Box::new(self.statement()?);
 
-        Ok(Stmt::While { condition, body })
+        Ok(Stmt::While {
+            span: self.span_from(keyword.span),
+            keyword,
+            condition,
+            body,
+        })
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, Error> {
+        let start = self.peek().span;
         let expr = self.expression()?;
-        self.consume(&TokenType::Semicolon, "Expect ';' after value.")?;
-        Ok(Stmt::Expression { expr })
+        self.consume(&TokenType::Semicolon, "Expect ';' after expression.")?;
+        Ok(Stmt::Expression {
+            expr,
+            span: self.span_from(start),
+        })
     }
 
     fn function(&mut self, kind: &str) -> Result<Stmt, Error> {
+        // Functions start at `fun`, while methods have no keyword and start at their name.
+        let start = if self.previous().r#type == TokenType::Fun {
+            self.previous().span
+        } else {
+            self.peek().span
+        };
         let name = self.consume(&TokenType::Identifier, &format!("Expect {kind} name."))?;
         self.consume(
             &TokenType::LeftParen,
@@ -242,7 +385,12 @@ impl Parser {
         if !self.check(&TokenType::RightParen) {
             loop {
                 if params.len() >= 255 {
-                    self.error(&self.peek(), "Cannot have more than 255 parameters.");
+                    let error = self.error(
+                        &self.peek(),
+                        "E0102",
+                        "Can't have more than 255 parameters.",
+                    );
+                    self.record(error);
                 }
                 params.push(self.consume(&TokenType::Identifier, "Expect parameter name.")?);
 
@@ -261,14 +409,23 @@ impl Parser {
 
         let body = self.block()?;
 
-        Ok(Stmt::Function { name, params, body })
+        Ok(Stmt::Function {
+            name,
+            params,
+            body,
+            span: self.span_from(start),
+        })
     }
 
     fn block(&mut self) -> Result<Vec<Stmt>, Error> {
+        self.nested(Self::block_inner)
+    }
+
+    fn block_inner(&mut self) -> Result<Vec<Stmt>, Error> {
         let mut statements = vec![];
 
         while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
-            statements.push(self.declaration()?);
+            statements.extend(self.declaration());
         }
 
         self.consume(&TokenType::RightBrace, "Expect '}' after block.")?;
@@ -277,35 +434,129 @@ impl Parser {
     }
 
     fn assignment(&mut self) -> Result<Expr, Error> {
-        let expr = self.or()?;
+        let expr = self.pipeline()?;
 
         if self.r#match(&[TokenType::Equal]) {
             let equals = &self.previous();
-            let value = Box::new(self.assignment()?);
+            let value = Box::new(self.expression()?);
+            let span = expr.span().to(value.span());
 
             match expr {
-                Expr::Variable { name } => return Ok(Expr::Assign { name, value }),
-                Expr::Get { object, name } => {
+                Expr::Variable { id, name, .. } => {
+                    return Ok(Expr::Assign {
+                        id,
+                        name,
+                        value,
+                        span,
+                    })
+                }
+                Expr::Get { object, name, .. } => {
                     return Ok(Expr::Set {
                         object,
                         name,
                         value,
+                        span,
                     })
                 }
-                _ => return Err(self.error(equals, "Invalid assignment target.")),
+                Expr::Tuple { elements, .. }
+                    if elements
+                        .iter()
+                        .all(|element| matches!(element, Expr::Variable { .. })) =>
+                {
+                    let (ids, names) = elements
+                        .into_iter()
+                        .filter_map(|element| match element {
+                            Expr::Variable { id, name, .. } => Some((id, name)),
+                            _ => None,
+                        })
+                        .unzip();
+                    return Ok(Expr::AssignTuple {
+                        ids,
+                        names,
+                        equals: equals.clone(),
+                        value,
+                        span,
+                    });
+                }
+                _ => return Err(self.error(equals, "E0101", "Invalid assignment target.")),
             }
         }
 
         Ok(expr)
     }
 
+    fn pipeline(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.or()?;
+
+        while self.r#match(&[TokenType::Pipe]) {
+            let pipe = self.previous();
+            let target = self.call()?;
+            expr = Self::pipe_into(expr, target, pipe);
+        }
+
+        Ok(expr)
+    }
+
+    /// Desugars `value |> target` by passing `value` as the first argument of the call on the
+    /// right, or by calling `target` with `value` alone when it isn't a call. Either way `value`
+    /// is evaluated first, even when the call is inside a `?.` chain that stops early.
+    fn pipe_into(value: Expr, target: Expr, pipe: Token) -> Expr {
+        let span = value.span().to(target.span());
+        match target {
+            Expr::Call {
+                callee,
+                paren,
+                mut arguments,
+                ..
+            } => {
+                arguments.insert(0, value);
+                Expr::Call {
+                    callee,
+                    paren,
+                    arguments,
+                    piped: true,
+                    span,
+                }
+            }
+            Expr::OptionalChain { expr, .. } => Expr::OptionalChain {
+                expr: Box::new(Self::pipe_into(value, *expr, pipe)),
+                span,
+            },
+            callee => Expr::Call {
+                callee: Box::new(callee),
+                paren: pipe,
+                arguments: vec![value],
+                piped: true,
+                span,
+            },
+        }
+    }
+
     fn or(&mut self) -> Result<Expr, Error> {
-        let mut expr = self.and()?;
+        let mut expr = self.coalesce()?;
 
         while self.r#match(&[TokenType::Or]) {
+            let operator = self.previous();
+            let right = Box::new(self.coalesce()?);
+            expr = Expr::Logical {
+                span: expr.span().to(right.span()),
+                left: Box::new(expr),
+                operator,
+                right,
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn coalesce(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.and()?;
+
+        while self.r#match(&[TokenType::QuestionQuestion]) {
             let operator = self.previous();
             let right = Box::new(self.and()?);
             expr = Expr::Logical {
+                span: expr.span().to(right.span()),
                 left: Box::new(expr),
                 operator,
                 right,
@@ -318,10 +569,11 @@ impl Parser {
     fn and(&mut self) -> Result<Expr, Error> {
         let mut expr = self.equality()?;
 
-        while self.r#match(&[TokenType::Or]) {
+        while self.r#match(&[TokenType::And]) {
             let operator = self.previous();
             let right = Box::new(self.equality()?);
             expr = Expr::Logical {
+                span: expr.span().to(right.span()),
                 left: Box::new(expr),
                 operator,
                 right,
@@ -338,6 +590,7 @@ impl Parser {
             let operator = self.previous();
             let right = self.comparison()?;
             expr = Expr::Binary {
+                span: expr.span().to(right.span()),
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
@@ -348,7 +601,7 @@ impl Parser {
     }
 
     fn comparison(&mut self) -> Result<Expr, Error> {
-        let mut expr = self.term()?;
+        let mut expr = self.range()?;
 
         while self.r#match(&[
             TokenType::Greater,
@@ -356,9 +609,27 @@ impl Parser {
             TokenType::Less,
             TokenType::LessEqual,
         ]) {
+            let operator = self.previous();
+            let right = self.range()?;
+            expr = Expr::Binary {
+                span: expr.span().to(right.span()),
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn range(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.term()?;
+
+        if self.r#match(&[TokenType::DotDot, TokenType::DotDotEqual]) {
             let operator = self.previous();
             let right = self.term()?;
             expr = Expr::Binary {
+                span: expr.span().to(right.span()),
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
@@ -375,6 +646,7 @@ impl Parser {
             let operator = self.previous();
             let right = self.factor()?;
             expr = Expr::Binary {
+                span: expr.span().to(right.span()),
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
@@ -391,6 +663,7 @@ impl Parser {
             let operator = self.previous();
             let right = self.unary()?;
             expr = Expr::Binary {
+                span: expr.span().to(right.span()),
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
@@ -403,8 +676,9 @@ impl Parser {
     fn unary(&mut self) -> Result<Expr, Error> {
         if self.r#match(&[TokenType::Bang, TokenType::Minus]) {
             let operator = self.previous();
-            let right = self.unary()?;
+            let right = self.nested(Self::unary)?;
             Ok(Expr::Unary {
+                span: operator.span.to(right.span()),
                 operator,
                 right: Box::new(right),
             })
@@ -418,10 +692,12 @@ impl Parser {
 
         if !self.check(&TokenType::RightParen) {
             loop {
-                arguments.push(self.expression()?);
                 if arguments.len() >= 255 {
-                    self.error(&self.peek(), "Can't have more than 255 arguments.");
+                    let error =
+                        self.error(&self.peek(), "E0102", "Can't have more than 255 arguments.");
+                    self.record(error);
                 }
+                arguments.push(self.expression()?);
                 if !self.r#match(&[TokenType::Comma]) {
                     break;
                 }
@@ -431,74 +707,145 @@ impl Parser {
         let paren = self.consume(&TokenType::RightParen, "Expect ')' after arguments.")?;
 
         Ok(Expr::Call {
+            span: callee.span().to(paren.span),
             callee: Box::new(callee),
             paren,
             arguments,
+            piped: false,
         })
     }
 
     fn call(&mut self) -> Result<Expr, Error> {
         let mut expr = self.primary()?;
+        let mut optional = false;
 
         loop {
             if self.r#match(&[TokenType::LeftParen]) {
                 expr = self.finish_call(expr)?;
             } else if self.r#match(&[TokenType::Dot]) {
-                let name = self.consume(&TokenType::Identifier, "Expect property after '.'/")?;
+                let name =
+                    self.consume(&TokenType::Identifier, "Expect property name after '.'.")?;
                 expr = Expr::Get {
+                    span: expr.span().to(name.span),
                     object: Box::new(expr),
                     name,
                 }
+            } else if self.r#match(&[TokenType::LeftBracket]) {
+                let index = self.expression()?;
+                let bracket = self.consume(&TokenType::RightBracket, "Expect ']' after index.")?;
+                expr = Expr::Index {
+                    span: expr.span().to(bracket.span),
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                };
+            } else if self.r#match(&[TokenType::QuestionDot]) {
+                let name =
+                    self.consume(&TokenType::Identifier, "Expect property name after '?.'.")?;
+                expr = Expr::OptionalGet {
+                    span: expr.span().to(name.span),
+                    object: Box::new(expr),
+                    name,
+                };
+                optional = true;
             } else {
                 break;
             }
         }
 
+        // A nil receiver anywhere in the chain short-circuits the whole chain.
+        if optional {
+            expr = Expr::OptionalChain {
+                span: expr.span(),
+                expr: Box::new(expr),
+            };
+        }
+
         Ok(expr)
     }
 
+    fn finish_tuple(&mut self, start: Span, first: Expr) -> Result<Expr, Error> {
+        let mut elements = vec![first];
+
+        while !self.check(&TokenType::RightParen) {
+            elements.push(self.expression()?);
+            if !self.r#match(&[TokenType::Comma]) {
+                break;
+            }
+        }
+
+        self.consume(&TokenType::RightParen, "Expect ')' after tuple elements.")?;
+
+        Ok(Expr::Tuple {
+            elements,
+            span: self.span_from(start),
+        })
+    }
+
     fn primary(&mut self) -> Result<Expr, Error> {
         let token = self.peek();
 
         let expr = if self.r#match(&[TokenType::False]) {
             Expr::Literal {
                 value: Object::Bool(false),
+                span: token.span,
             }
         } else if self.r#match(&[TokenType::True]) {
             Expr::Literal {
                 value: Object::Bool(true),
+                span: token.span,
             }
         } else if self.r#match(&[TokenType::This]) {
             Expr::This {
-                keyword: self.peek().clone(),
+                id: self.id(),
+                keyword: token.clone(),
+                span: token.span,
             }
         } else if self.r#match(&[TokenType::Nil]) {
-            Expr::Literal { value: Object::Nil }
+            Expr::Literal {
+                value: Object::Nil,
+                span: token.span,
+            }
         } else if self.r#match(&[TokenType::String, TokenType::Number]) {
             Expr::Literal {
                 value: token.literal.unwrap_or_default(),
+                span: token.span,
             }
         } else if self.r#match(&[TokenType::Identifier]) {
             Expr::Variable {
+                id: self.id(),
                 name: self.previous().clone(),
+                span: token.span,
             }
         } else if self.r#match(&[TokenType::LeftParen]) {
+            if self.r#match(&[TokenType::RightParen]) {
+                return Ok(Expr::Tuple {
+                    elements: vec![],
+                    span: self.span_from(token.span),
+                });
+            }
             let expr = self.expression()?;
-            self.consume(&TokenType::RightParen, "Expected ')' after expression.")?;
+            if self.r#match(&[TokenType::Comma]) {
+                return self.finish_tuple(token.span, expr);
+            }
+            self.consume(&TokenType::RightParen, "Expect ')' after expression.")?;
             Expr::Grouping {
                 expr: Box::new(expr),
+                span: self.span_from(token.span),
             }
         } else if self.r#match(&[TokenType::Super]) {
-            let keyword = self.advance().clone();
+            let keyword = token.clone();
             self.consume(&TokenType::Dot, "Expect '.' after 'super'.")?;
             let method = self.consume(&TokenType::Identifier, "Expect superclass method name.")?;
 
-            Expr::Super { keyword, method }
+            Expr::Super {
+                id: self.id(),
+                keyword,
+                method,
+                span: self.span_from(token.span),
+            }
         } else {
-            return Err(Error::Runtime {
-                token,
-                message: "Expected expression".to_string(),
-            });
+            return Err(self.error(&token, "E0100", "Expect expression."));
         };
         Ok(expr)
     }
@@ -543,10 +890,15 @@ impl Parser {
         if self.check(token_type) {
             Ok(self.advance().clone())
         } else {
-            Err(self.error(&self.peek(), message))
+            Err(self.error(&self.peek(), "E0100", message))
         }
     }
 
+    /// The span from `start` to the end of the most recently consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous().span)
+    }
+
     fn synchronize(&mut self) {
         self.advance();
 
@@ -571,10 +923,33 @@ impl Parser {
         }
     }
 
-    fn error(&self, token: &Token, message: &str) -> Error {
-        parser_error(token, message);
+    /// Keeps a syntax error to report, unless it repeats the last one, as happens when every
+    /// block left open at the end of the file fails in the same way, or follows the input
+    /// nesting too deeply.
+    fn record(&mut self, error: Error) {
+        if self.too_deep {
+            return;
+        }
+        let repeated = match (self.errors.last(), &error) {
+            (
+                Some(Error::Parse {
+                    token: last,
+                    message: last_message,
+                    ..
+                }),
+                Error::Parse { token, message, .. },
+            ) => last.span == token.span && last_message == message,
+            _ => false,
+        };
+        if !repeated {
+            self.errors.push(error);
+        }
+    }
+
+    fn error(&self, token: &Token, code: &'static str, message: &str) -> Error {
         Error::Parse {
             token: token.clone(),
+            code,
             message: message.to_string(),
         }
     }
@@ -603,4 +978,15 @@ mod tests {
     test_parser!(parse_true, "true;");
     test_parser!(parse_false, "false;");
     test_parser!(parse_nil, "nil;");
+    test_parser!(optional_chain, "a?.b.c();");
+    test_parser!(nil_coalescing, "a ?? b ?? c;");
+    test_parser!(pipeline, "x |> f |> g(1);");
+    test_parser!(slice, "s[1..n + 1];");
+    test_parser!(tuple, "var (a, b) = (1, (2,));");
+    test_parser!(assign_tuple, "(a, b) = (b, a);");
+    test_parser!(
+        collects_errors,
+        "print ;\nvar = 1;\n{ print (; }\nprint 1;\nfun f( {}"
+    );
+    test_parser!(multiline_spans, "if (ready)\n  print \"héllo\";");
 }
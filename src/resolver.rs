@@ -1,10 +1,11 @@
-use crate::error::{report, Error};
-use crate::expr::{expr, Expr};
-use crate::interpreter::Interpreter;
+use crate::diagnostic::{Diagnostic, Severity, Stage};
+use crate::error::Error;
+use crate::expr::{expr, Expr, ExprId};
+use crate::interpreter::{Interpreter, Slot};
 use crate::stmt::{stmt, Stmt};
-use crate::token::{Object, Token, TokenType};
+use crate::token::{Object, Token};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::mem;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -22,12 +23,91 @@ enum ClassType {
     Subclass,
 }
 
+/// Something the resolver can warn about. Each kind can be allowed on its own, and none of them
+/// stop the program from running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Warning {
+    UnusedVariable,
+    UnusedParameter,
+    UnreachableCode,
+    ShadowedVariable,
+    UnusedAssignment,
+    ArityMismatch,
+}
+
+impl Warning {
+    pub const ALL: [Warning; 6] = [
+        Warning::UnusedVariable,
+        Warning::UnusedParameter,
+        Warning::UnreachableCode,
+        Warning::ShadowedVariable,
+        Warning::UnusedAssignment,
+        Warning::ArityMismatch,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Warning::UnusedVariable => "W0001",
+            Warning::UnusedParameter => "W0002",
+            Warning::UnreachableCode => "W0003",
+            Warning::ShadowedVariable => "W0004",
+            Warning::UnusedAssignment => "W0005",
+            Warning::ArityMismatch => "W0006",
+        }
+    }
+
+    /// The name used to allow the warning on the command line, as in `--allow=unused-variable`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Warning::UnusedVariable => "unused-variable",
+            Warning::UnusedParameter => "unused-parameter",
+            Warning::UnreachableCode => "unreachable-code",
+            Warning::ShadowedVariable => "shadowed-variable",
+            Warning::UnusedAssignment => "unused-assignment",
+            Warning::ArityMismatch => "arity-mismatch",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|warning| warning.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LocalKind {
+    Variable,
+    Parameter,
+    Function,
+    Class,
+    /// `this` and `super`, which the user never declares.
+    Implicit,
+}
+
+#[derive(Debug, Clone)]
+struct Local {
+    name: Token,
+    kind: LocalKind,
+    defined: bool,
+    read: bool,
+    assigned: bool,
+    /// The parameter count while the local is known to hold a function declared with `fun`.
+    arity: Option<usize>,
+    /// Where the local lives in its scope's environment, counting declarations from zero.
+    slot: usize,
+}
+
 pub struct Resolver<'i> {
     interpreter: &'i mut Interpreter,
-    scopes: Vec<HashMap<String, bool>>,
+    scopes: Vec<HashMap<String, Local>>,
+    /// Arities of global functions, dropped as soon as the name could hold something else.
+    global_functions: HashMap<String, usize>,
+    /// Globals the program declares more than once, whose calls could reach any of the
+    /// declarations, so their arity is never known.
+    redeclared_globals: HashSet<String>,
     current_function: FunctionType,
     current_class: ClassType,
-    pub had_error: bool,
+    allowed: HashSet<Warning>,
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl<'i> Resolver<'i> {
@@ -35,19 +115,39 @@ impl<'i> Resolver<'i> {
         Resolver {
             interpreter,
             scopes: vec![],
+            global_functions: HashMap::new(),
+            redeclared_globals: HashSet::new(),
             current_function: FunctionType::None,
             current_class: ClassType::None,
-            had_error: false,
+            allowed: HashSet::new(),
+            diagnostics: vec![],
         }
     }
 
-    fn error(&mut self, token: &Token, message: &str) {
-        if token.r#type == TokenType::Eof {
-            report(token.line, " at end", message);
-        } else {
-            report(token.line, &format!(" at '{}'", token.lexeme), message);
+    /// Stops reporting one kind of warning.
+    pub fn allow(&mut self, warning: Warning) {
+        self.allowed.insert(warning);
+    }
+
+    pub fn had_error(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    fn error(&mut self, token: &Token, code: &'static str, message: &str) {
+        self.diagnostics
+            .push(Diagnostic::at_token(Stage::Resolve, code, token, message));
+    }
+
+    fn warn(&mut self, warning: Warning, diagnostic: Diagnostic) {
+        if !self.allowed.contains(&warning) {
+            self.diagnostics
+                .push(diagnostic.with_severity(Severity::Warning));
         }
-        self.had_error = true;
+    }
+
+    fn warn_at(&mut self, warning: Warning, token: &Token, message: &str) {
+        let diagnostic = Diagnostic::at_token(Stage::Resolve, warning.code(), token, message);
+        self.warn(warning, diagnostic);
     }
 
     fn resolve_stmt(&mut self, statement: &Stmt) -> Result<(), Error> {
@@ -55,12 +155,50 @@ impl<'i> Resolver<'i> {
     }
 
     pub fn resolve_stmts(&mut self, statements: &[Stmt]) -> Result<(), Error> {
-        for statement in statements {
+        if self.scopes.is_empty() {
+            self.find_redeclared_globals(statements);
+        }
+        let mut returns = statements
+            .iter()
+            .position(|statement| matches!(statement, Stmt::Return { .. }));
+        for (i, statement) in statements.iter().enumerate() {
+            // One warning covers the rest of the block.
+            if returns.is_some_and(|index| i == index + 1) {
+                let span = statement.span();
+                let diagnostic = Diagnostic::new(
+                    Stage::Resolve,
+                    Warning::UnreachableCode.code(),
+                    "Unreachable code.",
+                    span.line,
+                    span,
+                )
+                .with_note("the statement before it always returns.");
+                self.warn(Warning::UnreachableCode, diagnostic);
+                returns = None;
+            }
             self.resolve_stmt(statement)?
         }
         Ok(())
     }
 
+    fn find_redeclared_globals(&mut self, statements: &[Stmt]) {
+        let mut declared = HashSet::new();
+        for statement in statements {
+            let names = match statement {
+                Stmt::Function { name, .. } | Stmt::Var { name, .. } | Stmt::Class { name, .. } => {
+                    std::slice::from_ref(name)
+                }
+                Stmt::VarTuple { names, .. } => names.as_slice(),
+                _ => &[],
+            };
+            for name in names {
+                if !declared.insert(name.lexeme.as_str()) {
+                    self.redeclared_globals.insert(name.lexeme.clone());
+                }
+            }
+        }
+    }
+
     fn resolve_expr(&mut self, expression: &Expr) -> Result<(), Error> {
         expression.accept(self)
     }
@@ -70,24 +208,134 @@ impl<'i> Resolver<'i> {
     }
 
     fn end_scope(&mut self) {
-        self.scopes.pop();
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+
+        let mut unread: Vec<Local> = scope
+            .into_values()
+            .filter(|local| {
+                matches!(local.kind, LocalKind::Variable | LocalKind::Parameter)
+                    && !local.read
+                    && !local.name.lexeme.starts_with('_')
+            })
+            .collect();
+        unread.sort_by_key(|local| local.name.span.start);
+
+        for local in unread {
+            let name = &local.name;
+            let (warning, message) = match local.kind {
+                _ if local.assigned => (
+                    Warning::UnusedAssignment,
+                    format!("Variable '{}' is assigned but never read.", name.lexeme),
+                ),
+                LocalKind::Parameter => (
+                    Warning::UnusedParameter,
+                    format!("Unused parameter '{}'.", name.lexeme),
+                ),
+                _ => (
+                    Warning::UnusedVariable,
+                    format!("Unused local variable '{}'.", name.lexeme),
+                ),
+            };
+            let diagnostic = Diagnostic::at_token(Stage::Resolve, warning.code(), name, &message)
+                .with_help(&format!(
+                    "if this is intentional, prefix it with an underscore: '_{}'.",
+                    name.lexeme
+                ));
+            self.warn(warning, diagnostic);
+        }
     }
 
-    fn declare(&mut self, name: &Token) {
-        let mut already_defined = false;
-        if let Some(ref mut scope) = self.scopes.last_mut() {
-            already_defined = scope.contains_key(&name.lexeme);
-            scope.insert(name.lexeme.clone(), false);
+    fn declare(&mut self, name: &Token, kind: LocalKind) {
+        let Some(scope) = self.scopes.last() else {
+            self.global_functions.remove(&name.lexeme);
+            return;
         };
 
-        if already_defined {
-            self.error(name, "Variable with this name already declared in scope.");
+        if scope.contains_key(&name.lexeme) {
+            self.error(
+                name,
+                "E0201",
+                "Already a variable with this name in this scope.",
+            );
+        } else if let Some(shadowed) = self.scopes[..self.scopes.len() - 1]
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme))
+        {
+            let message = format!(
+                "'{}' shadows a local declared on line {}.",
+                name.lexeme, shadowed.name.line
+            );
+            self.warn_at(Warning::ShadowedVariable, name, &message);
+        }
+
+        if let Some(scope) = self.scopes.last_mut() {
+            let local = Local {
+                name: name.clone(),
+                kind,
+                defined: false,
+                read: false,
+                assigned: false,
+                arity: None,
+                slot: scope.len(),
+            };
+            scope.insert(name.lexeme.clone(), local);
         }
     }
 
     fn define(&mut self, name: &Token) {
-        if let Some(ref mut scope) = self.scopes.last_mut() {
-            scope.insert(name.lexeme.clone(), true);
+        if let Some(local) = self.local_in_current_scope(name) {
+            local.defined = true;
+        }
+    }
+
+    fn define_implicit(&mut self, name: &str) {
+        let scope = self.scopes.last_mut().expect("Scopes is empty.");
+        let local = Local {
+            name: Token::default(),
+            kind: LocalKind::Implicit,
+            defined: true,
+            read: true,
+            assigned: false,
+            arity: None,
+            slot: scope.len(),
+        };
+        scope.insert(name.to_owned(), local);
+    }
+
+    /// The innermost local with this name, if it isn't a global.
+    fn local_mut(&mut self, name: &str) -> Option<&mut Local> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    fn local_in_current_scope(&mut self, name: &Token) -> Option<&mut Local> {
+        self.scopes
+            .last_mut()
+            .and_then(|scope| scope.get_mut(&name.lexeme))
+    }
+
+    /// Records a write to a variable, after which it may no longer hold a known function.
+    fn mark_assigned(&mut self, name: &Token) {
+        match self.local_mut(&name.lexeme) {
+            Some(local) => {
+                local.assigned = true;
+                local.arity = None;
+            }
+            None => {
+                self.global_functions.remove(&name.lexeme);
+            }
+        }
+    }
+
+    fn known_arity(&self, name: &str) -> Option<usize> {
+        match self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            Some(local) => local.arity,
+            None => self.global_functions.get(name).copied(),
         }
     }
 
@@ -101,7 +349,7 @@ impl<'i> Resolver<'i> {
 
         self.begin_scope();
         for param in params {
-            self.declare(param);
+            self.declare(param, LocalKind::Parameter);
             self.define(param);
         }
         self.resolve_stmts(body)?;
@@ -110,12 +358,26 @@ impl<'i> Resolver<'i> {
         Ok(())
     }
 
-    fn resolve_local(&mut self, name: &Token) {
-        for (i, scope) in self.scopes.iter().rev().enumerate() {
-            if scope.contains_key(&name.lexeme) {
-                self.interpreter.resolve(name, i);
+    fn resolve_local(&mut self, id: ExprId, name: &Token) {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(local) = scope.get(&name.lexeme) {
+                let index = local.slot;
+                self.interpreter.resolve(id, Slot { depth, index });
+                return;
             }
         }
+
+        // At the top level only globals are visible, so there are no locals to suggest.
+        if !self.scopes.is_empty() {
+            let visible = self
+                .scopes
+                .iter()
+                .flat_map(|scope| scope.iter())
+                .filter(|(_, local)| !matches!(local.kind, LocalKind::Implicit))
+                .map(|(name, _)| name.clone())
+                .collect();
+            self.interpreter.resolve_global(id, visible);
+        }
     }
 }
 
@@ -136,30 +398,71 @@ impl<'i> expr::Visitor<()> for Resolver<'i> {
         Ok(())
     }
 
+    fn visit_index_expr(
+        &mut self,
+        object: &Expr,
+        _bracket: &Token,
+        index: &Expr,
+    ) -> Result<(), Error> {
+        self.resolve_expr(object)?;
+        self.resolve_expr(index)?;
+        Ok(())
+    }
+
     fn visit_literal_expr(&self, _value: &Object) -> Result<(), Error> {
         Ok(())
     }
 
+    fn visit_tuple_expr(&mut self, elements: &[Expr]) -> Result<(), Error> {
+        for element in elements {
+            self.resolve_expr(element)?;
+        }
+        Ok(())
+    }
+
     fn visit_unary_expr(&mut self, _operator: &Token, right: &Expr) -> Result<(), Error> {
         self.resolve_expr(right)?;
         Ok(())
     }
 
-    fn visit_variable_expr(&mut self, name: &Token) -> Result<(), Error> {
+    fn visit_variable_expr(&mut self, id: ExprId, name: &Token) -> Result<(), Error> {
         if let Some(scope) = self.scopes.last() {
-            if let Some(flag) = scope.get(&name.lexeme) {
-                if !*flag {
-                    self.error(name, "Cannot read local variable in its own initializer.");
+            if let Some(local) = scope.get(&name.lexeme) {
+                if !local.defined {
+                    self.error(
+                        name,
+                        "E0202",
+                        "Can't read local variable in its own initializer.",
+                    );
                 }
             }
         };
-        self.resolve_local(name);
+        if let Some(local) = self.local_mut(&name.lexeme) {
+            local.read = true;
+        }
+        self.resolve_local(id, name);
+        Ok(())
+    }
+
+    fn visit_assign_expr(&mut self, id: ExprId, name: &Token, value: &Expr) -> Result<(), Error> {
+        self.resolve_expr(value)?;
+        self.mark_assigned(name);
+        self.resolve_local(id, name);
         Ok(())
     }
 
-    fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> Result<(), Error> {
+    fn visit_assign_tuple_expr(
+        &mut self,
+        ids: &[ExprId],
+        names: &[Token],
+        _equals: &Token,
+        value: &Expr,
+    ) -> Result<(), Error> {
         self.resolve_expr(value)?;
-        self.resolve_local(name);
+        for (id, name) in ids.iter().zip(names) {
+            self.mark_assigned(name);
+            self.resolve_local(*id, name);
+        }
         Ok(())
     }
 
@@ -179,7 +482,22 @@ impl<'i> expr::Visitor<()> for Resolver<'i> {
         callee: &Expr,
         _paren: &Token,
         arguments: &[Expr],
+        _piped: bool,
     ) -> Result<(), Error> {
+        if let Expr::Variable { name, .. } = callee {
+            match self.known_arity(&name.lexeme) {
+                Some(arity) if arity != arguments.len() => {
+                    let message = format!(
+                        "'{}' takes {} arguments but is called with {}.",
+                        name.lexeme,
+                        arity,
+                        arguments.len()
+                    );
+                    self.warn_at(Warning::ArityMismatch, name, &message);
+                }
+                _ => {}
+            }
+        }
         self.resolve_expr(callee)?;
         for argument in arguments {
             self.resolve_expr(argument)?;
@@ -192,33 +510,56 @@ impl<'i> expr::Visitor<()> for Resolver<'i> {
         Ok(())
     }
 
+    fn visit_optional_get_expr(&mut self, object: &Expr, _name: &Token) -> Result<(), Error> {
+        self.resolve_expr(object)?;
+        Ok(())
+    }
+
+    fn visit_optional_chain_expr(&mut self, expression: &Expr) -> Result<(), Error> {
+        self.resolve_expr(expression)?;
+        Ok(())
+    }
+
     fn visit_set_expr(&mut self, object: &Expr, _name: &Token, value: &Expr) -> Result<(), Error> {
         self.resolve_expr(value)?;
         self.resolve_expr(object)?;
         Ok(())
     }
 
-    fn visit_this_expr(&mut self, keyword: &Token) -> Result<(), Error> {
+    fn visit_this_expr(&mut self, id: ExprId, keyword: &Token) -> Result<(), Error> {
         match self.current_class {
-            ClassType::None => self.error(keyword, "Cannot use 'this' outside of a class."),
-            ClassType::Subclass | ClassType::Class => self.resolve_local(keyword),
+            ClassType::None => self.error(keyword, "E0203", "Can't use 'this' outside of a class."),
+            ClassType::Subclass | ClassType::Class => self.resolve_local(id, keyword),
         }
         Ok(())
     }
 
-    fn visit_super_expr(&mut self, keyword: &Token, _method: &Token) -> Result<(), Error> {
+    fn visit_super_expr(
+        &mut self,
+        id: ExprId,
+        keyword: &Token,
+        _method: &Token,
+    ) -> Result<(), Error> {
         match self.current_class {
-            ClassType::None => self.error(keyword, "Cannot use 'super' outside of a class."),
-            ClassType::Class => {
-                self.error(keyword, "Cannot use 'super' in a class with no superclass.")
+            ClassType::None => {
+                self.error(keyword, "E0204", "Can't use 'super' outside of a class.")
             }
-            _ => self.resolve_local(keyword),
+            ClassType::Class => self.error(
+                keyword,
+                "E0205",
+                "Can't use 'super' in a class with no superclass.",
+            ),
+            _ => self.resolve_local(id, keyword),
         }
         Ok(())
     }
 }
 
 impl<'i> stmt::Visitor<()> for Resolver<'i> {
+    fn visit_null_stmt(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
     fn visit_block_stmt(&mut self, statements: &[Stmt]) -> Result<(), Error> {
         self.begin_scope();
         self.resolve_stmts(statements)?;
@@ -237,8 +578,17 @@ impl<'i> stmt::Visitor<()> for Resolver<'i> {
         params: &[Token],
         body: &[Stmt],
     ) -> Result<(), Error> {
-        self.declare(name);
+        self.declare(name, LocalKind::Function);
         self.define(name);
+        match self.local_in_current_scope(name) {
+            Some(local) => local.arity = Some(params.len()),
+            None => {
+                if !self.redeclared_globals.contains(&name.lexeme) {
+                    self.global_functions
+                        .insert(name.lexeme.clone(), params.len());
+                }
+            }
+        }
 
         self.resolve_function(params, body, FunctionType::Function)?;
         Ok(())
@@ -265,12 +615,16 @@ impl<'i> stmt::Visitor<()> for Resolver<'i> {
 
     fn visit_return_stmt(&mut self, keyword: &Token, value: &Option<Expr>) -> Result<(), Error> {
         if let FunctionType::None = self.current_function {
-            self.error(keyword, "Cannot return from top-level code.");
+            self.error(keyword, "E0206", "Can't return from top-level code.");
         }
 
         if let Some(return_value) = value {
             if let FunctionType::Initializer = self.current_function {
-                self.error(keyword, "Cannot return value from initializer.");
+                self.error(
+                    keyword,
+                    "E0207",
+                    "Can't return a value from an initializer.",
+                );
             }
             self.resolve_expr(return_value)?;
         }
@@ -278,7 +632,7 @@ impl<'i> stmt::Visitor<()> for Resolver<'i> {
     }
 
     fn visit_var_stmt(&mut self, name: &Token, initializer: &Option<Expr>) -> Result<(), Error> {
-        self.declare(name);
+        self.declare(name, LocalKind::Variable);
         if let Some(init) = initializer {
             self.resolve_expr(init)?;
         }
@@ -286,7 +640,28 @@ impl<'i> stmt::Visitor<()> for Resolver<'i> {
         Ok(())
     }
 
-    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> Result<(), Error> {
+    fn visit_var_tuple_stmt(
+        &mut self,
+        _keyword: &Token,
+        names: &[Token],
+        initializer: &Expr,
+    ) -> Result<(), Error> {
+        for name in names {
+            self.declare(name, LocalKind::Variable);
+        }
+        self.resolve_expr(initializer)?;
+        for name in names {
+            self.define(name);
+        }
+        Ok(())
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        _keyword: &Token,
+        condition: &Expr,
+        body: &Stmt,
+    ) -> Result<(), Error> {
         self.resolve_expr(condition)?;
         self.resolve_stmt(body)?;
         Ok(())
@@ -300,35 +675,38 @@ impl<'i> stmt::Visitor<()> for Resolver<'i> {
     ) -> Result<(), Error> {
         let enclosing_class = mem::replace(&mut self.current_class, ClassType::Class);
 
-        self.declare(name);
+        self.declare(name, LocalKind::Class);
         self.define(name);
 
         if let Some(Expr::Variable {
+            id,
             name: superclass_name,
+            ..
         }) = superclass
         {
             if name.lexeme == superclass_name.lexeme {
-                self.error(superclass_name, "A class cannot inherit from itself.");
+                self.error(
+                    superclass_name,
+                    "E0208",
+                    "A class can't inherit from itself.",
+                );
             }
 
             self.current_class = ClassType::Subclass;
-            self.resolve_local(superclass_name);
+            self.resolve_local(*id, superclass_name);
 
             self.begin_scope();
-            self.scopes
-                .last_mut()
-                .expect("Scopes is empty.")
-                .insert("super".to_owned(), true);
+            self.define_implicit("super");
         }
 
         self.begin_scope();
-        self.scopes
-            .last_mut()
-            .expect("Scopes is empty.")
-            .insert("this".to_owned(), true);
+        self.define_implicit("this");
 
         for method in methods {
-            if let Stmt::Function { name, params, body } = method {
+            if let Stmt::Function {
+                name, params, body, ..
+            } = method
+            {
                 let declaration = if name.lexeme == "init" {
                     FunctionType::Initializer
                 } else {
@@ -1,8 +1,11 @@
-use std::collections::HashMap;
+mod xid;
+
+use std::{collections::HashMap, str::Chars};
 
 use crate::{
-    error::error,
-    token::{Object, Token, TokenType},
+    bigint::BigInt,
+    diagnostic::{Diagnostic, Stage},
+    token::{Object, Span, Token, TokenType},
 };
 
 #[derive(Default, Debug, Clone, PartialEq)]
@@ -12,14 +15,26 @@ pub struct Scanner {
     pub start: usize,
     pub current: usize,
     pub line: usize,
+    /// Where the current line begins, for computing columns.
+    pub line_start: usize,
+    pub start_line: usize,
+    pub start_column: usize,
+    /// The byte offset of each character, plus one past the end of the source.
+    pub offsets: Vec<usize>,
+    /// Lexical errors, in the order they were found. Scanning carries on past each one.
+    pub diagnostics: Vec<Diagnostic>,
     pub keywords: HashMap<String, TokenType>,
 }
 
 impl Scanner {
     pub fn new(source: String) -> Self {
+        let mut offsets: Vec<usize> = source.char_indices().map(|(offset, _)| offset).collect();
+        offsets.push(source.len());
+
         Self {
             source: source.chars().collect(),
             line: 1,
+            offsets,
             keywords: HashMap::from([
                 ("and".to_string(), TokenType::And),
                 ("class".to_string(), TokenType::Class),
@@ -45,9 +60,14 @@ impl Scanner {
     pub fn scan_tokens(&mut self) -> Vec<Token> {
         while !self.is_at_end() {
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column(self.current);
             self.scan_token();
         }
 
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column(self.current);
         self.add_token(TokenType::Eof, None);
         self.tokens.clone()
     }
@@ -61,7 +81,17 @@ impl Scanner {
             '{' => self.add_token(TokenType::LeftBrace, None),
             '}' => self.add_token(TokenType::RightBrace, None),
             ',' => self.add_token(TokenType::Comma, None),
-            '.' => self.add_token(TokenType::Dot, None),
+            '[' => self.add_token(TokenType::LeftBracket, None),
+            ']' => self.add_token(TokenType::RightBracket, None),
+            '.' => {
+                if !self.r#match('.') {
+                    self.add_token(TokenType::Dot, None);
+                } else if self.r#match('=') {
+                    self.add_token(TokenType::DotDotEqual, None);
+                } else {
+                    self.add_token(TokenType::DotDot, None);
+                }
+            }
             '-' => self.add_token(TokenType::Minus, None),
             '+' => self.add_token(TokenType::Plus, None),
             ';' => self.add_token(TokenType::Semicolon, None),
@@ -70,23 +100,47 @@ impl Scanner {
             '=' => self.add_relational_token(TokenType::Equal, TokenType::EqualEqual),
             '<' => self.add_relational_token(TokenType::Less, TokenType::LessEqual),
             '>' => self.add_relational_token(TokenType::Greater, TokenType::GreaterEqual),
+            '?' => {
+                if self.r#match('.') {
+                    self.add_token(TokenType::QuestionDot, None);
+                } else if self.r#match('?') {
+                    self.add_token(TokenType::QuestionQuestion, None);
+                } else {
+                    self.error("E0001", "Unexpected character.");
+                }
+            }
+            '|' => {
+                if self.r#match('>') {
+                    self.add_token(TokenType::Pipe, None);
+                } else {
+                    self.error("E0001", "Unexpected character.");
+                }
+            }
             '/' => {
                 if self.r#match('/') {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                } else if self.r#match('*') {
+                    self.block_comment();
                 } else {
                     self.add_token(TokenType::Slash, None);
                 }
             }
-            'a'..='z' | 'A'..='Z' | '_' => self.identifier(),
+            'r' if self.peek() == '"' => {
+                self.advance();
+                self.raw_string();
+            }
             ' ' | '\r' | '\t' => {}
-            '\n' => {
-                self.line += 1;
+            '\n' => self.newline(),
+            '"' if self.peek() == '"' && self.peek_next() == '"' => {
+                self.current += 2;
+                self.block_string();
             }
             '"' => self.string(),
             '0'..='9' => self.number(),
-            _ => error(self.line, "Unexpected character."),
+            c if is_identifier_start(c) => self.identifier(),
+            _ => self.error("E0001", "Unexpected character."),
         }
     }
 
@@ -100,23 +154,156 @@ impl Scanner {
     }
 
     fn number(&mut self) {
-        while self.peek().is_ascii_digit() {
+        let radix = match (self.source[self.start], self.peek()) {
+            ('0', 'x' | 'X') => 16,
+            ('0', 'o' | 'O') => 8,
+            ('0', 'b' | 'B') => 2,
+            _ => 10,
+        };
+
+        if radix == 10 {
+            self.decimal_number();
+        } else {
             self.advance();
+            self.radix_number(radix);
         }
+    }
 
+    fn decimal_number(&mut self) {
+        if !self.digits(10) {
+            return;
+        }
+
+        let mut is_float = false;
         if self.peek() == '.' && self.peek_next().is_ascii_digit() {
+            is_float = true;
             self.advance();
+            if !self.digits(10) {
+                return;
+            }
+        }
 
-            while self.peek().is_ascii_digit() {
+        if matches!(self.peek(), 'e' | 'E') {
+            is_float = true;
+            self.advance();
+            if matches!(self.peek(), '+' | '-') {
                 self.advance();
             }
+            if !self.peek().is_ascii_digit() {
+                self.error("E0004", "Expect digits in exponent.");
+                return;
+            }
+            if !self.digits(10) {
+                return;
+            }
         }
 
-        let str_value: String = self.source[self.start..self.current].iter().collect();
+        let text: String = self.source[self.start..self.current]
+            .iter()
+            .filter(|c| **c != '_')
+            .collect();
 
-        let value: f64 = str_value.parse().unwrap();
+        if is_float {
+            if self.invalid_suffix() {
+                return;
+            }
+            match text.parse() {
+                Ok(value) => self.add_token(TokenType::Number, Some(Object::Number(value))),
+                Err(_) => self.error("E0004", "Invalid number literal."),
+            }
+        } else {
+            self.integer_literal(&text, 10);
+        }
+    }
+
+    /// Scans the digits of a `0x`, `0o` or `0b` literal, whose prefix has been consumed.
+    fn radix_number(&mut self, radix: u32) {
+        let digits_start = self.current;
+        if !self.digits(radix) {
+            return;
+        }
 
-        self.add_token(TokenType::Number, Some(Object::Number(value)));
+        if self.current == digits_start {
+            let prefix: String = self.source[self.start..self.current].iter().collect();
+            self.error("E0004", &format!("Expect digits after '{}'.", prefix));
+            return;
+        }
+
+        if self.peek().is_ascii_alphanumeric() && self.peek() != 'n' {
+            self.error(
+                "E0004",
+                &format!("Invalid digit '{}' in number literal.", self.peek()),
+            );
+            while self.peek().is_ascii_alphanumeric() {
+                self.advance();
+            }
+            return;
+        }
+
+        let digits: String = self.source[digits_start..self.current]
+            .iter()
+            .filter(|c| **c != '_')
+            .collect();
+        self.integer_literal(&digits, radix);
+    }
+
+    /// Consumes digits in the given radix, allowing `_` separators only between two digits.
+    fn digits(&mut self, radix: u32) -> bool {
+        let mut valid = true;
+        while self.peek().is_digit(radix) || self.peek() == '_' {
+            if self.peek() == '_'
+                && !(self.source[self.current - 1].is_digit(radix)
+                    && self.peek_next().is_digit(radix))
+            {
+                valid = false;
+            }
+            self.advance();
+        }
+
+        if !valid {
+            self.error("E0004", "Digit separator must be between digits.");
+        }
+        valid
+    }
+
+    /// Scans an integer, which is an `Integer` when it fits in 64 bits and a `BigInt` when it
+    /// doesn't or ends in `n`, so that `-9223372036854775808` can be written.
+    fn integer_literal(&mut self, digits: &str, radix: u32) {
+        // `n` only marks a BigInt when it ends the literal, so `10nx` is an error, not `10n x`.
+        let is_big = self.peek() == 'n' && !is_identifier_continue(self.peek_next());
+        if is_big {
+            self.advance();
+        }
+        if self.invalid_suffix() {
+            return;
+        }
+
+        let value = match i64::from_str_radix(digits, radix) {
+            Ok(value) if !is_big => Some(Object::Integer(value)),
+            _ => BigInt::from_str_radix(digits, radix).map(Object::BigInt),
+        };
+        match value {
+            Some(value) => self.add_token(TokenType::Number, Some(value)),
+            None => self.error("E0004", "Invalid number literal."),
+        }
+    }
+
+    /// Reports identifier characters running straight on from a number, like the `x` in `10x`,
+    /// which would otherwise scan as a separate identifier and give a confusing parse error.
+    fn invalid_suffix(&mut self) -> bool {
+        if !is_identifier_continue(self.peek()) {
+            return false;
+        }
+        let suffix_start = self.current;
+        while is_identifier_continue(self.peek()) {
+            self.advance();
+        }
+        let suffix: String = self.source[suffix_start..self.current].iter().collect();
+        self.error(
+            "E0004",
+            &format!("Invalid suffix '{}' on number literal.", suffix),
+        );
+        true
     }
 
     fn peek(&self) -> char {
@@ -146,32 +333,104 @@ impl Scanner {
 
     fn string(&mut self) {
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            if self.advance_in_string() == '\\' && !self.is_at_end() {
+                self.advance_in_string();
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            error(self.line, "Unterminated string.");
+            self.error("E0002", "Unterminated string.");
             return;
         }
 
         self.advance();
 
-        let value: String = self.source[self.start + 1..self.current - 1]
+        let raw: String = self.source[self.start + 1..self.current - 1]
+            .iter()
+            .collect();
+        self.add_string(&raw);
+    }
+
+    /// Scans a `r"..."` string, whose prefix has been consumed. Backslashes are kept as written.
+    fn raw_string(&mut self) {
+        while self.peek() != '"' && !self.is_at_end() {
+            self.advance_in_string();
+        }
+
+        if self.is_at_end() {
+            self.error("E0002", "Unterminated string.");
+            return;
+        }
+
+        self.advance();
+
+        let value: String = self.source[self.start + 2..self.current - 1]
             .iter()
             .collect();
         self.add_token(TokenType::String, Some(Object::String(value)));
     }
 
+    /// Scans a `"""..."""` string, whose opening quotes have been consumed.
+    fn block_string(&mut self) {
+        while !(self.peek() == '"'
+            && self.peek_next() == '"'
+            && self.source.get(self.current + 2) == Some(&'"'))
+        {
+            if self.is_at_end() {
+                self.error("E0002", "Unterminated string.");
+                return;
+            }
+            if self.advance_in_string() == '\\' && !self.is_at_end() {
+                self.advance_in_string();
+            }
+        }
+
+        let raw: String = self.source[self.start + 3..self.current].iter().collect();
+        self.current += 3;
+        self.add_string(&dedent(&raw));
+    }
+
+    fn advance_in_string(&mut self) -> char {
+        let c = self.advance();
+        if c == '\n' {
+            self.newline();
+        }
+        c
+    }
+
+    fn add_string(&mut self, raw: &str) {
+        match unescape(raw) {
+            Ok(value) => self.add_token(TokenType::String, Some(Object::String(value))),
+            Err(message) => self.error("E0003", &message),
+        }
+    }
+
     fn advance(&mut self) -> char {
         self.current += 1;
         self.source[self.current - 1]
     }
 
+    /// Skips a `/* ... */` comment, whose opening has been consumed. Comments nest, so each `/*`
+    /// needs its own `*/`.
+    fn block_comment(&mut self) {
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                self.error("E0002", "Unterminated block comment.");
+                return;
+            }
+
+            match self.advance() {
+                '/' if self.r#match('*') => depth += 1,
+                '*' if self.r#match('/') => depth -= 1,
+                '\n' => self.newline(),
+                _ => {}
+            }
+        }
+    }
+
     fn identifier(&mut self) {
-        while self.peek().is_ascii_alphanumeric() {
+        while is_identifier_continue(self.peek()) {
             self.advance();
         }
 
@@ -184,20 +443,148 @@ impl Scanner {
         }
     }
 
+    /// Moves to the next line after consuming a `\n`.
+    fn newline(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
+    fn column(&self, position: usize) -> usize {
+        position - self.line_start + 1
+    }
+
+    /// The span of the lexeme scanned so far.
+    fn span(&self) -> Span {
+        Span {
+            start: self.offsets[self.start],
+            end: self.offsets[self.current],
+            line: self.start_line,
+            column: self.start_column,
+            end_line: self.line,
+            end_column: self.column(self.current),
+        }
+    }
+
     fn add_token(&mut self, token_type: TokenType, literal: Option<Object>) {
         self.tokens.push(Token {
             r#type: token_type,
             lexeme: self.source[self.start..self.current].iter().collect(),
             literal,
             line: self.line,
+            span: self.span(),
         });
     }
 
+    fn error(&mut self, code: &'static str, message: &str) {
+        self.diagnostics.push(Diagnostic::new(
+            Stage::Scan,
+            code,
+            message,
+            self.line,
+            self.span(),
+        ));
+    }
+
     fn is_at_end(&self) -> bool {
         self.current >= self.source.len()
     }
 }
 
+/// Identifiers follow Unicode's XID grammar: an `XID_Start` character or `_`, followed by any
+/// number of `XID_Continue` characters.
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || xid::is_xid_start(c)
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    xid::is_xid_continue(c)
+}
+
+/// Decodes the escape sequences in the body of a string literal.
+fn unescape(raw: &str) -> Result<String, String> {
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some('\\') => value.push('\\'),
+            Some('"') => value.push('"'),
+            Some('u') => value.push(unicode_escape(&mut chars)?),
+            Some(other) => return Err(format!("Invalid escape sequence '\\{}'.", other)),
+            None => return Err("Invalid escape sequence '\\'.".to_string()),
+        }
+    }
+    Ok(value)
+}
+
+/// Decodes the `{1F600}` part of a `\u{1F600}` escape.
+fn unicode_escape(chars: &mut Chars) -> Result<char, String> {
+    let rest = chars.as_str();
+    let digits = rest
+        .strip_prefix('{')
+        .and_then(|rest| rest.split_once('}'))
+        .map(|(digits, _)| digits)
+        .filter(|digits| {
+            (1..=6).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_hexdigit())
+        })
+        .ok_or("Unicode escape must be '\\u{' followed by 1 to 6 hex digits and '}'.")?;
+
+    let c = u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| format!("Invalid unicode code point '{}'.", digits))?;
+    *chars = rest[digits.len() + 2..].chars();
+    Ok(c)
+}
+
+/// Strips the line break after the opening quotes of a block string, the blank line before its
+/// closing quotes, and the spaces and tabs that start every non-blank line. Only an identical
+/// prefix counts, so a tab on one line and spaces on another share no indentation.
+fn dedent(raw: &str) -> String {
+    let raw = raw
+        .strip_prefix("\r\n")
+        .or_else(|| raw.strip_prefix('\n'))
+        .unwrap_or(raw);
+    let mut lines: Vec<&str> = raw.split('\n').collect();
+    if lines.len() > 1 && lines.last().is_some_and(|line| is_blank(line)) {
+        lines.pop();
+    }
+
+    let indent = lines
+        .iter()
+        .filter(|line| !is_blank(line))
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+        .reduce(|common, indent| {
+            let shared = common
+                .bytes()
+                .zip(indent.bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            &common[..shared]
+        })
+        .unwrap_or("");
+
+    lines
+        .iter()
+        .map(|line| {
+            line.strip_prefix(indent)
+                .unwrap_or_else(|| line.trim_start_matches([' ', '\t']))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim_start_matches([' ', '\t', '\r']).is_empty()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -220,4 +607,24 @@ mod tests {
         while_loop,
         "var x = 10;\n while x <= 20\n{ x += 1;\n print x;\n}"
     );
+    test_scanner!(
+        unicode_identifiers,
+        "var my_var = café + 変数 /* a /* nested */ comment */ + _x1;"
+    );
+    #[test]
+    fn collects_lexical_errors() {
+        let mut scanner = Scanner::new("var a = 0x;\nprint \"\\q\" @ 1_;".to_string());
+        scanner.scan_tokens();
+        assert_debug_snapshot!(scanner.diagnostics);
+    }
+
+    test_scanner!(bigint_literal, "12345678901234567890n;");
+    test_scanner!(
+        string_literals,
+        "\"tab\\there \\\"quoted\\\" \\u{1F600}\"\nr\"C:\\path\"\n\"\"\"\n    a\n      b\n    \"\"\";"
+    );
+    test_scanner!(
+        numeric_literals,
+        "0xFF 0o17 0b1010 1_000_000 1e-9 2.5E3 0xffn"
+    );
 }
@@ -0,0 +1,130 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::token::Object;
+
+/// A lazy integer range produced by `start..end` or `start..=end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub start: i64,
+    pub end: i64,
+    pub inclusive: bool,
+}
+
+impl Range {
+    /// The end of the range as an exclusive bound.
+    fn exclusive_end(&self) -> i64 {
+        if self.inclusive {
+            self.end.saturating_add(1)
+        } else {
+            self.end
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        usize::try_from(self.exclusive_end().saturating_sub(self.start)).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.inclusive {
+            write!(f, "{}..={}", self.start, self.end)
+        } else {
+            write!(f, "{}..{}", self.start, self.end)
+        }
+    }
+}
+
+/// The number of elements in a string, list, tuple or range, counting strings by Unicode scalar
+/// values.
+pub fn length(sequence: &Object) -> Option<usize> {
+    match sequence {
+        Object::String(string) => Some(string.chars().count()),
+        Object::List(list) => Some(list.borrow().len()),
+        Object::Tuple(elements) => Some(elements.len()),
+        Object::Range(range) => Some(range.len()),
+        _ => None,
+    }
+}
+
+/// Evaluates `sequence[index]`, where `index` is either a single position or a range to slice.
+/// Negative positions count from the end of the sequence.
+pub fn index(sequence: &Object, index: &Object) -> Result<Object, String> {
+    let len = length(sequence).ok_or("Can only index strings, lists, tuples and ranges.")?;
+    match index {
+        Object::Integer(position) => {
+            let position = resolve(*position, len)
+                .filter(|position| *position < len)
+                .ok_or_else(|| format!("Index {} out of bounds for length {}.", position, len))?;
+            Ok(element(sequence, position))
+        }
+        Object::Range(range) => {
+            let (start, end) = bounds(range, len)
+                .ok_or_else(|| format!("Slice {} out of bounds for length {}.", range, len))?;
+            Ok(slice(sequence, start, end))
+        }
+        Object::Number(_) => Err("Index must be an integer.".to_string()),
+        _ => Err("Index must be an integer or a range.".to_string()),
+    }
+}
+
+fn resolve(position: i64, len: usize) -> Option<usize> {
+    let len = i64::try_from(len).ok()?;
+    let position = if position < 0 {
+        position.checked_add(len)?
+    } else {
+        position
+    };
+    usize::try_from(position)
+        .ok()
+        .filter(|position| *position <= len as usize)
+}
+
+fn bounds(range: &Range, len: usize) -> Option<(usize, usize)> {
+    let start = resolve(range.start, len)?;
+    let end = resolve(range.end, len)?;
+    let end = if range.inclusive { end + 1 } else { end };
+    if start <= end && end <= len {
+        Some((start, end))
+    } else {
+        None
+    }
+}
+
+fn element(sequence: &Object, position: usize) -> Object {
+    match sequence {
+        Object::String(string) => Object::String(
+            string
+                .chars()
+                .nth(position)
+                .map(String::from)
+                .unwrap_or_default(),
+        ),
+        Object::List(list) => list.borrow()[position].clone(),
+        Object::Tuple(elements) => elements[position].clone(),
+        Object::Range(range) => Object::Integer(range.start + position as i64),
+        _ => unreachable!(),
+    }
+}
+
+fn slice(sequence: &Object, start: usize, end: usize) -> Object {
+    match sequence {
+        Object::String(string) => {
+            Object::String(string.chars().skip(start).take(end - start).collect())
+        }
+        Object::List(list) => {
+            Object::List(Rc::new(RefCell::new(list.borrow()[start..end].to_vec())))
+        }
+        Object::Tuple(elements) => Object::Tuple(Rc::new(elements[start..end].to_vec())),
+        Object::Range(range) => Object::Range(Range {
+            start: range.start + start as i64,
+            end: range.start + end as i64,
+            inclusive: false,
+        }),
+        _ => unreachable!(),
+    }
+}
@@ -1,43 +1,63 @@
 use crate::error::Error;
-use crate::{expr::Expr, token::Token};
+use crate::{
+    expr::Expr,
+    token::{Span, Token},
+};
 
 #[derive(Default, Debug, Clone, PartialEq)]
 pub enum Stmt {
     Block {
         statements: Vec<Stmt>,
+        span: Span,
     },
     Expression {
         expr: Expr,
+        span: Span,
     },
     Function {
         name: Token,
         params: Vec<Token>,
         body: Vec<Stmt>,
+        span: Span,
     },
     Print {
         expr: Expr,
+        span: Span,
     },
     Var {
         name: Token,
         initializer: Option<Expr>,
+        span: Span,
+    },
+    VarTuple {
+        keyword: Token,
+        names: Vec<Token>,
+        initializer: Expr,
+        span: Span,
     },
     Return {
         keyword: Token,
         value: Option<Expr>,
+        span: Span,
     },
     If {
         condition: Expr,
         then_branch: Box<Stmt>,
         else_branch: Box<Option<Stmt>>,
+        span: Span,
     },
     While {
+        /// The `while` or `for` that starts the loop.
+        keyword: Token,
         condition: Expr,
         body: Box<Stmt>,
+        span: Span,
     },
     Class {
         name: Token,
         superclass: Option<Expr>,
         methods: Vec<Stmt>,
+        span: Span,
     },
     #[default]
     Null,
@@ -49,17 +69,29 @@ pub mod stmt {
     use super::{Error, Stmt};
 
     pub trait Visitor<R> {
+        fn visit_null_stmt(&mut self) -> Result<R, Error>;
         fn visit_block_stmt(&mut self, statements: &[Stmt]) -> Result<R, Error>;
         fn visit_expression_stmt(&mut self, expression: &Expr) -> Result<R, Error>;
         fn visit_print_stmt(&mut self, expression: &Expr) -> Result<R, Error>;
         fn visit_var_stmt(&mut self, name: &Token, initializer: &Option<Expr>) -> Result<R, Error>;
+        fn visit_var_tuple_stmt(
+            &mut self,
+            keyword: &Token,
+            names: &[Token],
+            initializer: &Expr,
+        ) -> Result<R, Error>;
         fn visit_if_stmt(
             &mut self,
             condition: &Expr,
             then_branch: &Stmt,
             else_branch: &Option<Stmt>,
         ) -> Result<R, Error>;
-        fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> Result<R, Error>;
+        fn visit_while_stmt(
+            &mut self,
+            keyword: &Token,
+            condition: &Expr,
+            body: &Stmt,
+        ) -> Result<R, Error>;
         fn visit_function_stmt(
             &mut self,
             name: &Token,
@@ -77,27 +109,59 @@ pub mod stmt {
 }
 
 impl Stmt {
+    /// The source range of the whole statement, from its first token to its last.
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Block { span, .. }
+            | Stmt::Expression { span, .. }
+            | Stmt::Function { span, .. }
+            | Stmt::Print { span, .. }
+            | Stmt::Var { span, .. }
+            | Stmt::VarTuple { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::While { span, .. }
+            | Stmt::Class { span, .. } => *span,
+            Stmt::Null => Span::default(),
+        }
+    }
+
     pub fn accept<R>(&self, visitor: &mut dyn stmt::Visitor<R>) -> Result<R, Error> {
         match self {
-            Stmt::Block { statements } => visitor.visit_block_stmt(statements),
-            Stmt::Expression { expr } => visitor.visit_expression_stmt(expr),
-            Stmt::Print { expr } => visitor.visit_print_stmt(expr),
-            Stmt::Var { name, initializer } => visitor.visit_var_stmt(name, initializer),
+            Stmt::Block { statements, .. } => visitor.visit_block_stmt(statements),
+            Stmt::Expression { expr, .. } => visitor.visit_expression_stmt(expr),
+            Stmt::Print { expr, .. } => visitor.visit_print_stmt(expr),
+            Stmt::Var {
+                name, initializer, ..
+            } => visitor.visit_var_stmt(name, initializer),
+            Stmt::VarTuple {
+                keyword,
+                names,
+                initializer,
+                ..
+            } => visitor.visit_var_tuple_stmt(keyword, names, initializer),
             Stmt::If {
                 condition,
                 then_branch,
                 else_branch,
+                ..
             } => visitor.visit_if_stmt(condition, then_branch, else_branch),
-            Stmt::While { condition, body } => visitor.visit_while_stmt(condition, body),
-            Stmt::Null => unimplemented!(),
-            Stmt::Function { name, params, body } => {
-                visitor.visit_function_stmt(name, params, body)
-            }
-            Stmt::Return { keyword, value } => visitor.visit_return_stmt(keyword, value),
+            Stmt::While {
+                keyword,
+                condition,
+                body,
+                ..
+            } => visitor.visit_while_stmt(keyword, condition, body),
+            Stmt::Null => visitor.visit_null_stmt(),
+            Stmt::Function {
+                name, params, body, ..
+            } => visitor.visit_function_stmt(name, params, body),
+            Stmt::Return { keyword, value, .. } => visitor.visit_return_stmt(keyword, value),
             Stmt::Class {
                 name,
                 superclass,
                 methods,
+                ..
             } => visitor.visit_class_stmt(name, superclass, methods),
         }
     }
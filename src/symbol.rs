@@ -0,0 +1,53 @@
+use std::{borrow::Borrow, cell::RefCell, collections::HashSet, fmt, rc::Rc};
+
+thread_local! {
+    static SYMBOLS: RefCell<HashSet<Rc<str>>> = RefCell::new(HashSet::new());
+}
+
+/// An interned name. Every symbol with the same text shares one allocation, so a field name is
+/// stored once however many instances have it. Symbols order and compare by their text.
+///
+/// The table holds on to every name interned on its thread until the thread exits, even after
+/// no instance has a field by that name any more. Names come from the program's source, so this
+/// is bounded, except for names a program builds at runtime and passes to `setField`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(Rc<str>);
+
+impl Symbol {
+    pub fn intern(name: &str) -> Self {
+        SYMBOLS.with(|symbols| {
+            let mut symbols = symbols.borrow_mut();
+            if let Some(symbol) = symbols.get(name) {
+                return Symbol(Rc::clone(symbol));
+            }
+            let symbol: Rc<str> = Rc::from(name);
+            symbols.insert(Rc::clone(&symbol));
+            Symbol(symbol)
+        })
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interns_equal_names_once() {
+        let first = Symbol::intern("field");
+        let second = Symbol::intern(&String::from("field"));
+        assert!(Rc::ptr_eq(&first.0, &second.0));
+        assert_ne!(first, Symbol::intern("other"));
+    }
+}
@@ -0,0 +1,153 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    diagnostic::{Diagnostic, Stage},
+    error::Error,
+    interpreter::Interpreter,
+    resolver::Resolver,
+    stmt::Stmt,
+    token::Token,
+};
+
+/// How one `test_*` function went.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestResult {
+    pub name: String,
+    pub duration: Duration,
+    /// The runtime error that failed the test, if it failed.
+    pub failure: Option<Diagnostic>,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// The tests in a script: its top-level functions that take no parameters and whose names start
+/// with `test_`, in the order they are declared.
+pub fn discover(statements: &[Stmt]) -> Vec<&Token> {
+    statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Stmt::Function { name, params, .. }
+                if name.lexeme.starts_with("test_") && params.is_empty() =>
+            {
+                Some(name)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Runs the script's top level once to define everything the tests use, then each of its tests
+/// in order, sharing whatever state the earlier ones left behind. Nothing the script prints is
+/// shown, so it can't get mixed up with the results. If the top level fails, every test fails
+/// with its error.
+pub fn run(statements: &[Stmt]) -> Vec<TestResult> {
+    let tests = discover(statements);
+    let Some(first) = tests.first().copied() else {
+        return vec![];
+    };
+    let mut interpreter = Interpreter::new().without_output();
+    let mut resolver = Resolver::new(&mut interpreter);
+    let resolved = resolver.resolve_stmts(statements);
+    let compile_error = resolver.diagnostics.into_iter().find(Diagnostic::is_error);
+    let setup_failure = match compile_error {
+        Some(error) => Some(error),
+        None => resolved
+            .and_then(|_| interpreter.interpret(statements))
+            .err()
+            .map(|error| failure(&mut interpreter, first, &error)),
+    };
+
+    tests
+        .into_iter()
+        .map(|name| {
+            let start = Instant::now();
+            let failure = match &setup_failure {
+                Some(error) => Some(error.clone()),
+                None => interpreter
+                    .call_global(name)
+                    .err()
+                    .map(|error| failure(&mut interpreter, name, &error)),
+            };
+            TestResult {
+                name: name.lexeme.clone(),
+                duration: start.elapsed(),
+                failure,
+            }
+        })
+        .collect()
+}
+
+fn failure(interpreter: &mut Interpreter, name: &Token, error: &Error) -> Diagnostic {
+    Diagnostic::from_error(error)
+        .unwrap_or_else(|| unexpected(name, error))
+        .with_traceback(interpreter.take_traceback())
+}
+
+/// Compile errors are caught before the test runs, so only a bug in the interpreter can end one
+/// with anything but a runtime error.
+fn unexpected(name: &Token, error: &Error) -> Diagnostic {
+    let message = format!("Test stopped unexpectedly: {}", error);
+    Diagnostic::at_token(Stage::Runtime, "E0300", name, &message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::Parser, scanner::Scanner};
+    use insta::assert_snapshot;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let tokens = Scanner::new(source.to_string()).scan_tokens();
+        Parser::new(tokens).parse().unwrap()
+    }
+
+    #[test]
+    fn discovers_top_level_tests_in_order() {
+        let statements = parse(
+            "fun test_b() {}\n\
+             fun helper() { fun test_nested() {} }\n\
+             fun test_with_params(a) {}\n\
+             class test_class {}\n\
+             fun test_a() {}",
+        );
+        let names: Vec<&str> = discover(&statements)
+            .iter()
+            .map(|name| name.lexeme.as_str())
+            .collect();
+        assert_eq!(names, ["test_b", "test_a"]);
+    }
+
+    #[test]
+    fn runs_the_top_level_once_for_all_tests() {
+        let statements = parse(
+            "var count = 0;\n\
+             count = count + 1;\n\
+             fun test_first() { count = count + 1; assertEqual(count, 2); }\n\
+             fun test_second() { count = count + 1; assertEqual(count, 3); }",
+        );
+        let results = run(&statements);
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(TestResult::passed));
+    }
+
+    #[test]
+    fn fails_every_test_when_the_top_level_fails() {
+        let statements = parse("fun test_a() {}\nfun test_b() {}\nprint missing;");
+        let results = run(&statements);
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| !result.passed()));
+    }
+
+    #[test]
+    fn reports_failed_assertions() {
+        let source = "fun check(value) {\n  assertEqual(value, \"1\");\n}\n\
+                      fun test_strings() { check(1); }";
+        let statements = parse(source);
+        let result = run(&statements).remove(0);
+        assert_snapshot!(result.failure.unwrap().render(source, false));
+    }
+}
@@ -6,8 +6,10 @@ use std::{
 };
 
 use crate::{
+    bigint::BigInt,
     class::{Class, Instance},
     function::Function,
+    sequence::Range,
 };
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -17,6 +19,8 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
@@ -33,6 +37,11 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    DotDot,
+    DotDotEqual,
+    QuestionDot,
+    QuestionQuestion,
+    Pipe,
     // Literals
     Identifier,
     String,
@@ -58,12 +67,48 @@ pub enum TokenType {
     Eof,
 }
 
-#[derive(Default, Debug, Clone, PartialEq)]
+/// A range of source text. Offsets are in bytes with an exclusive end, while lines and columns
+/// are 1-based and count Unicode scalar values, so `end_column` is the column just past the end.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Span {
+    /// The span from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end,
+            end_line: other.end_line,
+            end_column: other.end_column,
+            ..self
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone)]
 pub struct Token {
     pub r#type: TokenType,
     pub lexeme: String,
     pub literal: Option<Object>,
     pub line: usize,
+    pub span: Span,
+}
+
+// Positions other than the line are left out of equality and hashing, so a token still matches
+// the same name written elsewhere on its line.
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.r#type == other.r#type
+            && self.lexeme == other.lexeme
+            && self.literal == other.literal
+            && self.line == other.line
+    }
 }
 
 impl Hash for Token {
@@ -82,6 +127,8 @@ impl fmt::Display for Token {
             (TokenType::RightParen, _) => ")".to_string(),
             (TokenType::LeftBrace, _) => "{".to_string(),
             (TokenType::RightBrace, _) => "}".to_string(),
+            (TokenType::LeftBracket, _) => "[".to_string(),
+            (TokenType::RightBracket, _) => "]".to_string(),
             (TokenType::Comma, _) => ",".to_string(),
             (TokenType::Dot, _) => ".".to_string(),
             (TokenType::Minus, _) => "-".to_string(),
@@ -97,6 +144,11 @@ impl fmt::Display for Token {
             (TokenType::GreaterEqual, _) => ">=".to_string(),
             (TokenType::Less, _) => "<".to_string(),
             (TokenType::LessEqual, _) => "<=".to_string(),
+            (TokenType::DotDot, _) => "..".to_string(),
+            (TokenType::DotDotEqual, _) => "..=".to_string(),
+            (TokenType::QuestionDot, _) => "?.".to_string(),
+            (TokenType::QuestionQuestion, _) => "??".to_string(),
+            (TokenType::Pipe, _) => "|>".to_string(),
             (TokenType::Identifier, Some(val))
             | (TokenType::String, Some(val))
             | (TokenType::Number, Some(val)) => val.to_string(),
@@ -119,7 +171,7 @@ impl fmt::Display for Token {
             (TokenType::Eof, _) => "eof".to_string(),
             (TokenType::Identifier, None)
             | (TokenType::String, None)
-            | (TokenType::Number, None) => panic!("Invalid token"),
+            | (TokenType::Number, None) => self.lexeme.clone(),
         };
 
         f.write_str(&val)
@@ -131,11 +183,16 @@ impl fmt::Display for Token {
 pub enum Object {
     String(String),
     Number(f64),
+    Integer(i64),
+    BigInt(BigInt),
     Identifier(String),
     Bool(bool),
     Callable(Function),
     Class(Rc<RefCell<Class>>),
     Instance(Rc<RefCell<Instance>>),
+    List(Rc<RefCell<Vec<Object>>>),
+    Tuple(Rc<Vec<Object>>),
+    Range(Range),
     #[default]
     Nil,
 }
@@ -147,17 +204,50 @@ impl PartialEq for Object {
             (_, Object::Nil) | (Object::Nil, _) => false,
             (Object::Bool(left), Object::Bool(right)) => left == right,
             (Object::Number(left), Object::Number(right)) => left == right,
+            (Object::Integer(left), Object::Integer(right)) => left == right,
+            (Object::Integer(int), Object::Number(float))
+            | (Object::Number(float), Object::Integer(int)) => integer_equals_float(*int, *float),
+            (Object::BigInt(left), Object::BigInt(right)) => left == right,
+            (Object::BigInt(big), Object::Integer(int))
+            | (Object::Integer(int), Object::BigInt(big)) => *big == BigInt::from(*int),
+            (Object::BigInt(big), Object::Number(float))
+            | (Object::Number(float), Object::BigInt(big)) => {
+                BigInt::from_f64(*float).as_ref() == Some(big)
+            }
             (Object::String(left), Object::String(right)) => left == right,
-            (Object::Class(left), Object::Class(right)) => left == right,
+            (Object::Callable(left), Object::Callable(right)) => left == right,
+            (Object::Class(left), Object::Class(right)) => Rc::ptr_eq(left, right),
+            (Object::Instance(left), Object::Instance(right)) => Rc::ptr_eq(left, right),
+            (Object::List(left), Object::List(right)) => left == right,
+            (Object::Tuple(left), Object::Tuple(right)) => left == right,
+            (Object::Range(left), Object::Range(right)) => left == right,
             _ => false,
         }
     }
 }
 
+/// An integer equals a float only when the float holds exactly the same integral value, so
+/// `1 == 1.0` but `9007199254740993 != 9007199254740992.0`.
+fn integer_equals_float(int: i64, float: f64) -> bool {
+    float.fract() == 0.0
+        && float >= i64::MIN as f64
+        && float < i64::MAX as f64
+        && float as i64 == int
+}
+
 impl Object {
     pub fn is_truthy(&self) -> bool {
         !matches!(self, Object::Bool(false) | Object::Nil)
     }
+
+    /// The value of a number as a float, promoting integers.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Object::Number(num) => Some(*num),
+            Object::Integer(int) => Some(*int as f64),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for Object {
@@ -165,14 +255,40 @@ impl fmt::Display for Object {
         match self {
             Object::String(str) => f.write_str(str),
             Object::Number(num) => f.write_str(&num.to_string()),
+            Object::Integer(int) => f.write_str(&int.to_string()),
+            Object::BigInt(big) => write!(f, "{}", big),
             Object::Identifier(ident) => f.write_str(ident),
             Object::Bool(b) => f.write_str(&b.to_string()),
             Object::Nil => f.write_str("nil"),
-            Object::Callable(_) => f.write_str("callable"),
+            Object::Callable(function) => write!(f, "{}", function),
             Object::Class(class) => write!(f, "{}", class.borrow()),
             Object::Instance(instance) => {
                 write!(f, "{} instance", instance.borrow().class.borrow().name)
             }
+            Object::List(list) => {
+                f.write_str("[")?;
+                for (i, element) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                f.write_str("]")
+            }
+            Object::Tuple(elements) => {
+                f.write_str("(")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                if elements.len() == 1 {
+                    f.write_str(",")?;
+                }
+                f.write_str(")")
+            }
+            Object::Range(range) => write!(f, "{}", range),
         }
     }
 }
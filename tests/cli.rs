@@ -40,3 +40,152 @@ fn reference_files() {
         assert_yaml_snapshot!(test_output);
     });
 }
+
+fn run_with_flags(flags: &[&str], path: &str) -> Output {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.args(flags).arg(path).output().unwrap()
+}
+
+#[test]
+fn warnings_do_not_change_exit_code() {
+    let output = run_with_flags(&[], "test-files/warnings/unused_parameter.lox");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("warning[W0002]"));
+}
+
+#[test]
+fn deny_warnings() {
+    let output = run_with_flags(
+        &["--deny-warnings"],
+        "test-files/warnings/unused_parameter.lox",
+    );
+    assert_eq!(output.status.code(), Some(65));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn allow_warning() {
+    let output = run_with_flags(
+        &["--deny-warnings", "--allow=unused-parameter"],
+        "test-files/warnings/unused_parameter.lox",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn json_error_format() {
+    let output = run_with_flags(
+        &["--error-format=json"],
+        "test-files/suggestion/global_variable.lox",
+    );
+    assert_eq!(output.status.code(), Some(70));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stderr.lines().collect();
+    assert_eq!(
+        lines,
+        [concat!(
+            r#"{"severity":"error","stage":"runtime","code":"E0301","#,
+            r#""message":"Undefined variable 'countr'.","#,
+            r#""file":"test-files/suggestion/global_variable.lox","line":2,"column":7,"#,
+            r#""span":{"start":23,"end":29,"line":2,"column":7,"end_line":2,"end_column":13},"#,
+            r#""notes":[],"help":"did you mean 'counter'?","traceback":[]}"#
+        )]
+    );
+}
+
+#[test]
+fn json_error_format_for_command_line_problems() {
+    let output = run_with_flags(&["--error-format=json"], "test-files/missing.lox");
+    assert_eq!(output.status.code(), Some(66));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        concat!(
+            r#"{"severity":"error","stage":"cli","code":"E0401","#,
+            r#""message":"Could not read 'test-files/missing.lox': No such file or directory (os error 2).","#,
+            r#""file":"test-files/missing.lox","line":0,"column":null,"span":null,"#,
+            r#""notes":[],"help":null,"traceback":[]}"#,
+            "\n"
+        )
+    );
+
+    let output = run_with_flags(&["--allow=nonsense", "--error-format=json"], "a.lox");
+    assert_eq!(output.status.code(), Some(64));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with(r#"{"severity":"error","stage":"cli","code":"E0400","#));
+    assert_eq!(stderr.lines().count(), 1);
+
+    let output = run_with_flags(&["test", "--error-format=json"], "test-files/missing");
+    assert_eq!(output.status.code(), Some(66));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(r#""code":"E0401""#));
+}
+
+#[test]
+fn test_subcommand_passes_annotated_scripts() {
+    let output = run_with_flags(&["test"], "test-files/if");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with(" passed, 0 failed.\n"));
+}
+
+/// Checks every annotation in the suite, skipping the tests meant for other interpreters.
+#[test]
+fn test_subcommand_passes_every_reference_file() {
+    let output = run_with_flags(&["test"], "test-files");
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("scanning/"));
+    assert!(!stdout.contains("too_many_locals"));
+}
+
+#[test]
+fn test_subcommand_reports_failures() {
+    let script = std::env::temp_dir().join(format!("treelox-failing-{}.lox", std::process::id()));
+    std::fs::write(&script, "print 1; // expect: 1\nprint 3; // expect: 2\n").unwrap();
+    let output = run_with_flags(&["test"], script.to_str().unwrap());
+    std::fs::remove_file(&script).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("FAIL"));
+    assert!(stdout.contains("- 2\n"));
+    assert!(stdout.contains("+ 3\n"));
+    assert!(stdout.ends_with("0 passed, 1 failed.\n"));
+}
+
+#[test]
+fn test_subcommand_runs_test_functions() {
+    let script = std::env::temp_dir().join(format!("treelox-unit-{}.lox", std::process::id()));
+    std::fs::write(
+        &script,
+        "fun test_passes() { assertEqual(1 + 1, 2); }\nfun test_fails() { assertEqual(\"a\", 1); }\n",
+    )
+    .unwrap();
+    let output = run_with_flags(&["test"], script.to_str().unwrap());
+    std::fs::remove_file(&script).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("::test_passes ("));
+    assert!(stdout.contains("::test_fails ("));
+    assert!(stdout.contains("Assertion failed: expected 1 but got \"a\"."));
+    // The script itself passes too, since it prints nothing and has no annotations.
+    assert!(stdout.ends_with("2 passed, 1 failed.\n"));
+}
+
+#[test]
+fn test_subcommand_keeps_script_output_out_of_the_report() {
+    let script = std::env::temp_dir().join(format!("treelox-noisy-{}.lox", std::process::id()));
+    std::fs::write(
+        &script,
+        "print \"noise\"; // expect: noise\nfun test_one() { print \"more noise\"; }\nfun test_two() {}\n",
+    )
+    .unwrap();
+    let output = run_with_flags(&["test"], script.to_str().unwrap());
+    std::fs::remove_file(&script).unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("noise"));
+    assert_eq!(stdout.lines().count(), 3);
+    assert!(stdout.ends_with("3 passed, 0 failed.\n"));
+}
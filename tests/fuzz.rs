@@ -0,0 +1,69 @@
+//! Feeds every file under `test-files/fuzz`, and truncations and single-character deletions
+//! of it, through the scanner, parser, resolver and interpreter to check that no input makes
+//! them panic. The unmodified files are also run end to end by the reference-file snapshots in
+//! `cli.rs`.
+
+use std::{fs, thread};
+
+use insta::glob;
+use treelox::{interpreter::Interpreter, parser::Parser, resolver::Resolver, scanner::Scanner};
+
+/// The binary runs the interpreter on a thread this large, so the tests get the same here.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// How many places in each file get truncated or have a character deleted, spread evenly, so long
+/// files don't take quadratic time.
+const MUTATION_POINTS: usize = 200;
+
+/// How many loop iterations and calls each program may make, since a mutation can turn a loop
+/// into one that never ends.
+const STEP_LIMIT: usize = 10_000;
+
+fn check(source: String) {
+    let tokens = Scanner::new(source).scan_tokens();
+    if let Ok(statements) = Parser::new(tokens).parse() {
+        let mut interpreter = Interpreter::new().with_step_limit(STEP_LIMIT);
+        let mut resolver = Resolver::new(&mut interpreter);
+        let resolved = resolver.resolve_stmts(&statements);
+        if resolved.is_ok() && !resolver.had_error() {
+            let _ = interpreter.interpret(&statements);
+        }
+    }
+}
+
+fn mutations(source: &str) -> impl Iterator<Item = String> + '_ {
+    let step = source.len().div_ceil(MUTATION_POINTS).max(1);
+    let boundaries: Vec<usize> = source
+        .char_indices()
+        .map(|(i, _)| i)
+        .step_by(step)
+        .collect();
+    let truncations = boundaries
+        .clone()
+        .into_iter()
+        .map(|i| source[..i].to_string());
+    let deletions = boundaries.into_iter().map(|i| {
+        let mut mutated = source.to_string();
+        mutated.remove(i);
+        mutated
+    });
+    truncations.chain(deletions)
+}
+
+#[test]
+fn mutated_programs_never_panic() {
+    glob!("../test-files/fuzz", "*.lox", |path| {
+        let source = fs::read_to_string(path).unwrap();
+        thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                for mutated in mutations(&source) {
+                    check(mutated);
+                }
+                check(source);
+            })
+            .unwrap()
+            .join()
+            .unwrap_or_else(|_| panic!("{} made the interpreter panic", path.display()));
+    });
+}